@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::http::{Method, Uri};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use super::users::{verify_password, UserDatabase};
+use super::{quote, Access, Authenticator, Principal, Rejection};
+
+/// RFC 7617 Basic 认证
+pub struct BasicAuthenticator {
+    users: Arc<UserDatabase>,
+    realm: String,
+    /// 验证成功过的密码的 HMAC。客户端每个请求都会携带密码，缓存避免每次都计算 bcrypt/argon2
+    verified: Mutex<HashMap<String, [u8; 32]>>,
+    /// 缓存使用的随机密钥，内存中的缓存无法离线暴力破解
+    key: [u8; 32],
+}
+
+impl BasicAuthenticator {
+    pub fn new(users: Arc<UserDatabase>, realm: &str) -> Self {
+        Self {
+            users,
+            realm: realm.to_string(),
+            verified: Mutex::new(HashMap::new()),
+            key: super::random_key(),
+        }
+    }
+
+    fn digest(&self, name: &str, password: &str) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(name.as_bytes());
+        mac.update(&[0]);
+        mac.update(password.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    fn is_cached(&self, name: &str, digest: &[u8; 32]) -> bool {
+        self.verified
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(|cached| bool::from(cached.ct_eq(digest)))
+    }
+}
+
+#[async_trait]
+impl Authenticator for BasicAuthenticator {
+    fn scheme(&self) -> &'static str {
+        "Basic"
+    }
+
+    fn challenges(&self, _stale: bool) -> Vec<String> {
+        vec![format!("Basic realm={}, charset=\"UTF-8\"", quote(&self.realm))]
+    }
+
+    async fn authenticate(&self, _method: &Method, _uri: &Uri, credentials: &str) -> Result<Principal, Rejection> {
+        let decoded = STANDARD.decode(credentials.trim()).map_err(|_| Rejection::Invalid)?;
+        let decoded = String::from_utf8(decoded).map_err(|_| Rejection::Invalid)?;
+        let (name, password) = decoded.split_once(':').ok_or(Rejection::Invalid)?;
+        let Some(entry) = self.users.get(name) else {
+            // 用户不存在时同样对一个已有的哈希验证一次并忽略结果，响应时间不会暴露哪些用户名存在
+            if let Some(hash) = self.users.any_hash() {
+                let (hash, password) = (hash.to_string(), password.to_string());
+                let _ = tokio::task::spawn_blocking(move || verify_password(&hash, &password)).await;
+            }
+            return Err(Rejection::Invalid);
+        };
+        let principal = Principal {
+            name: name.to_string(),
+            admin: entry.admin,
+            access: Access::default(),
+        };
+
+        let digest = self.digest(name, password);
+        if self.is_cached(name, &digest) {
+            return Ok(principal);
+        }
+
+        let hash = entry.password_hash.clone();
+        let password = password.to_string();
+        let valid = tokio::task::spawn_blocking(move || verify_password(&hash, &password))
+            .await
+            .unwrap_or(false);
+        if !valid {
+            return Err(Rejection::Invalid);
+        }
+        self.verified.lock().unwrap().insert(name.to_string(), digest);
+        Ok(principal)
+    }
+}
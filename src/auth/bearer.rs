@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use async_trait::async_trait;
+use axum::http::{Method, Uri};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::{quote, Access, Authenticator, Principal, Rejection, Scope};
+
+/// JWT 中与访问范围有关的声明
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    scope: Scope,
+    /// 允许访问的 URL 路径前缀，未设置时不限制
+    path: Option<String>,
+}
+
+/// 用本地配置的密钥校验 JWT
+pub struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl JwtVerifier {
+    /// `algorithm` 为 `HS256`、`RS256` 或 `EdDSA`，`key` 分别是共享密钥或 PEM 格式的公钥
+    pub fn new(
+        algorithm: &str,
+        key: &[u8],
+        issuer: Option<&str>,
+        audience: Option<&str>,
+    ) -> Result<Self, String> {
+        let (algorithm, key) = match algorithm {
+            "HS256" => (Algorithm::HS256, DecodingKey::from_secret(key)),
+            "RS256" => (Algorithm::RS256, DecodingKey::from_rsa_pem(key).map_err(|e| e.to_string())?),
+            "EdDSA" => (Algorithm::EdDSA, DecodingKey::from_ed_pem(key).map_err(|e| e.to_string())?),
+            other => return Err(format!("unsupported JWT algorithm {:?}", other)),
+        };
+        let mut validation = Validation::new(algorithm);
+        // 配置了签发者或受众时，令牌中缺少对应的声明同样拒绝
+        let mut required = vec!["exp", "sub"];
+        if let Some(issuer) = issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        match audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                required.push("aud");
+            }
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required);
+        Ok(Self { key, validation })
+    }
+
+    fn verify(&self, token: &str) -> Option<Principal> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.key, &self.validation)
+            .ok()?
+            .claims;
+        Some(Principal {
+            name: claims.sub,
+            admin: false,
+            access: Access::restricted(claims.path.as_deref(), claims.scope).ok()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenFile {
+    #[serde(default)]
+    tokens: Vec<TokenConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TokenConfig {
+    /// 认证后的用户名
+    name: String,
+    /// 令牌的 SHA-256 十六进制摘要，文件中不保存令牌本身
+    sha256: String,
+    #[serde(default)]
+    scope: Scope,
+    path: Option<String>,
+}
+
+/// 不透明的 API 令牌，按令牌的 SHA-256 摘要查找
+#[derive(Debug, Clone, Default)]
+pub struct TokenDatabase {
+    tokens: HashMap<String, Principal>,
+}
+
+impl TokenDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// 读取 TOML 格式的令牌文件，每个 `[[tokens]]` 包含 `name`、`sha256`、`scope` 和 `path`
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: TokenFile = toml::from_str(&content).map_err(|e| e.to_string())?;
+        for token in file.tokens {
+            let hash = token.sha256.to_ascii_lowercase();
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("token {:?}: sha256 must be a hex SHA-256 digest", token.name));
+            }
+            let access = Access::restricted(token.path.as_deref(), token.scope)
+                .map_err(|e| format!("token {:?}: {}", token.name, e))?;
+            self.tokens.insert(
+                hash,
+                Principal {
+                    name: token.name,
+                    admin: false,
+                    access,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn get(&self, token: &str) -> Option<&Principal> {
+        self.tokens.get(&format!("{:x}", Sha256::digest(token.as_bytes())))
+    }
+}
+
+/// RFC 6750 Bearer 认证，先查找不透明令牌，再作为 JWT 校验
+pub struct BearerAuthenticator {
+    tokens: TokenDatabase,
+    jwt: Option<JwtVerifier>,
+    realm: String,
+}
+
+impl BearerAuthenticator {
+    pub fn new(tokens: TokenDatabase, jwt: Option<JwtVerifier>, realm: &str) -> Self {
+        Self {
+            tokens,
+            jwt,
+            realm: realm.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for BearerAuthenticator {
+    fn scheme(&self) -> &'static str {
+        "Bearer"
+    }
+
+    fn challenges(&self, _stale: bool) -> Vec<String> {
+        vec![format!("Bearer realm={}", quote(&self.realm))]
+    }
+
+    async fn authenticate(&self, _method: &Method, _uri: &Uri, credentials: &str) -> Result<Principal, Rejection> {
+        let token = credentials.trim();
+        if let Some(principal) = self.tokens.get(token) {
+            return Ok(principal.clone());
+        }
+        self.jwt
+            .as_ref()
+            .and_then(|jwt| jwt.verify(token))
+            .ok_or(Rejection::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn sign(claims: Value) -> String {
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    fn verifier() -> JwtVerifier {
+        JwtVerifier::new("HS256", SECRET, Some("issuer"), Some("webdav")).unwrap()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "sub": "ci",
+            "iss": "issuer",
+            "aud": "webdav",
+            "exp": now() + 3600,
+            "scope": "read-write",
+            "path": "/dav/builds",
+        })
+    }
+
+    #[test]
+    fn valid_token() {
+        let principal = verifier().verify(&sign(claims())).unwrap();
+        assert_eq!(principal.name, "ci");
+        assert!(!principal.admin);
+        assert!(!principal.access.read_only);
+        assert_eq!(principal.access.path_prefix.as_deref(), Some(Path::new("/dav/builds")));
+    }
+
+    #[test]
+    fn scope_defaults_to_read() {
+        let mut claims = claims();
+        claims.as_object_mut().unwrap().remove("scope");
+        assert!(verifier().verify(&sign(claims)).unwrap().access.read_only);
+    }
+
+    #[test]
+    fn rejects_invalid_claims() {
+        let cases = [
+            ("exp", json!(now() - 3600)),
+            ("aud", json!("other")),
+            ("iss", json!("other")),
+            ("path", json!("relative/path")),
+            ("path", json!("/dav/../etc")),
+            ("scope", json!("admin")),
+        ];
+        for (name, value) in cases {
+            let mut claims = claims();
+            claims[name] = value;
+            assert!(verifier().verify(&sign(claims)).is_none(), "accepted bad {}", name);
+        }
+        for name in ["sub", "exp", "aud", "iss"] {
+            let mut claims = claims();
+            claims.as_object_mut().unwrap().remove(name);
+            assert!(verifier().verify(&sign(claims)).is_none(), "accepted missing {}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_key_and_algorithm() {
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims(), &EncodingKey::from_secret(b"other"))
+            .unwrap();
+        assert!(verifier().verify(&token).is_none());
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::HS384), &claims(), &EncodingKey::from_secret(SECRET))
+            .unwrap();
+        assert!(verifier().verify(&token).is_none());
+        assert!(verifier().verify("not.a.jwt").is_none());
+        assert!(JwtVerifier::new("none", SECRET, None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn opaque_tokens_are_looked_up_by_digest() {
+        let mut tokens = TokenDatabase::new();
+        tokens.tokens.insert(
+            format!("{:x}", Sha256::digest(b"opaque")),
+            Principal {
+                name: "deploy".to_string(),
+                admin: false,
+                access: Access::default(),
+            },
+        );
+        let authenticator = BearerAuthenticator::new(tokens, Some(verifier()), "webdav");
+        let uri = Uri::from_static("/dav/");
+        let principal = authenticator.authenticate(&Method::GET, &uri, "opaque").await.unwrap();
+        assert_eq!(principal.name, "deploy");
+        let principal = authenticator.authenticate(&Method::GET, &uri, &sign(claims())).await.unwrap();
+        assert_eq!(principal.name, "ci");
+        assert_eq!(
+            authenticator.authenticate(&Method::GET, &uri, "wrong").await,
+            Err(Rejection::Invalid)
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use axum::http::{Method, Uri};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use super::users::UserDatabase;
+use super::{quote, Access, Authenticator, Principal, Rejection};
+
+/// 同时记录 nonce-count 的 nonce 数量上限，超出时丢弃最早签发的
+const MAX_NONCES: usize = 4096;
+
+/// RFC 7616 定义的摘要算法，不支持 `-sess` 变体
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// 质询中按优先顺序列出的算法
+    const PREFERRED: [DigestAlgorithm; 2] = [DigestAlgorithm::Sha256, DigestAlgorithm::Md5];
+
+    fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Sha256 => "SHA-256",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::PREFERRED
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    /// 计算十六进制小写摘要
+    pub fn hash(self, data: &str) -> String {
+        match self {
+            DigestAlgorithm::Md5 => format!("{:x}", Md5::digest(data.as_bytes())),
+            DigestAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data.as_bytes())),
+        }
+    }
+
+    /// 十六进制摘要的长度
+    fn hex_len(self) -> usize {
+        match self {
+            DigestAlgorithm::Md5 => 32,
+            DigestAlgorithm::Sha256 => 64,
+        }
+    }
+}
+
+/// 用户在某个 realm 下的 HA1 = H(username:realm:password)
+#[derive(Debug, Clone, Default)]
+pub struct DigestCredentials {
+    ha1: HashMap<(String, DigestAlgorithm), String>,
+}
+
+impl DigestCredentials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, algorithm: DigestAlgorithm, ha1: &str) {
+        self.ha1.insert((name.to_string(), algorithm), ha1.to_ascii_lowercase());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ha1.is_empty()
+    }
+
+    /// 读取 htdigest 格式的文件：每行 `name:realm:ha1`，只保留 `realm` 下的用户。
+    ///
+    /// HA1 为 32 位十六进制时是 MD5，64 位时是 SHA-256，同一用户可以各有一行。
+    pub fn load_htdigest<P: AsRef<Path>>(&mut self, path: P, realm: &str) -> io::Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, message))
+            };
+            // realm 中可以含有 `:`，用户名和 HA1 中不会
+            let (name, rest) = line.split_once(':').ok_or_else(|| invalid("expected name:realm:ha1"))?;
+            let (line_realm, ha1) = rest.rsplit_once(':').ok_or_else(|| invalid("expected name:realm:ha1"))?;
+            if name.is_empty() {
+                return Err(invalid("empty user name"));
+            }
+            let algorithm = DigestAlgorithm::PREFERRED
+                .into_iter()
+                .find(|algorithm| algorithm.hex_len() == ha1.len())
+                .filter(|_| ha1.bytes().all(|b| b.is_ascii_hexdigit()))
+                .ok_or_else(|| invalid("expected a hex MD5 or SHA-256 digest"))?;
+            if line_realm == realm {
+                self.insert(name, algorithm, ha1);
+            }
+        }
+        Ok(())
+    }
+
+    /// 文件中出现过的算法，只在质询中提供这些算法
+    fn algorithms(&self) -> Vec<DigestAlgorithm> {
+        DigestAlgorithm::PREFERRED
+            .into_iter()
+            .filter(|algorithm| self.ha1.keys().any(|(_, used)| used == algorithm))
+            .collect()
+    }
+
+    fn get(&self, name: &str, algorithm: DigestAlgorithm) -> Option<&str> {
+        self.ha1.get(&(name.to_string(), algorithm)).map(String::as_str)
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// nonce 中签发时间（毫秒时间戳）和随机数的字节数
+const NONCE_PAYLOAD_LEN: usize = 16;
+/// nonce 中截断的 HMAC 的字节数
+const NONCE_MAC_LEN: usize = 16;
+
+struct NonceCount {
+    issued: u64,
+    /// 已使用的最大 nonce-count
+    last_count: u32,
+}
+
+/// 服务端签发的 nonce：签发时间、随机数和它们的 HMAC，过期或用过的 nonce-count 不会被接受。
+///
+/// 签发时不保存状态，只有摘要校验通过的 nonce 才记录 nonce-count，
+/// 未认证的客户端无法通过索取质询占用内存。
+struct NonceStore {
+    key: [u8; 32],
+    counts: Mutex<NonceCounts>,
+    lifetime: Duration,
+}
+
+#[derive(Default)]
+struct NonceCounts {
+    counts: HashMap<String, NonceCount>,
+    /// 记录已满时丢弃过状态的 nonce 中最晚的签发时间，不晚于它签发的 nonce 不再接受
+    forgotten_until: Option<u64>,
+}
+
+impl NonceStore {
+    fn new(lifetime: Duration) -> Self {
+        Self {
+            key: super::random_key(),
+            counts: Mutex::new(NonceCounts::default()),
+            lifetime,
+        }
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload);
+        mac
+    }
+
+    fn issue(&self) -> String {
+        let mut nonce = [0; NONCE_PAYLOAD_LEN + NONCE_MAC_LEN];
+        nonce[..8].copy_from_slice(&now_millis().to_be_bytes());
+        nonce[8..NONCE_PAYLOAD_LEN].copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+        let tag = self.mac(&nonce[..NONCE_PAYLOAD_LEN]).finalize().into_bytes();
+        nonce[NONCE_PAYLOAD_LEN..].copy_from_slice(&tag[..NONCE_MAC_LEN]);
+        URL_SAFE_NO_PAD.encode(nonce)
+    }
+
+    /// 校验 nonce 的 HMAC，返回签发时间
+    fn issued(&self, nonce: &str) -> Option<u64> {
+        let nonce = URL_SAFE_NO_PAD.decode(nonce).ok()?;
+        if nonce.len() != NONCE_PAYLOAD_LEN + NONCE_MAC_LEN {
+            return None;
+        }
+        let (payload, tag) = nonce.split_at(NONCE_PAYLOAD_LEN);
+        self.mac(payload).verify_truncated_left(tag).ok()?;
+        Some(u64::from_be_bytes(payload[..8].try_into().unwrap()))
+    }
+
+    /// 记录 nonce-count 的使用。
+    ///
+    /// 不是本进程签发、过期或 nonce-count 没有递增（重放或乱序）时返回 `Stale`，
+    /// 调用前摘要已经校验通过，客户端用新的 nonce 重试即可。
+    fn use_count(&self, nonce: &str, count: u32) -> Result<(), Rejection> {
+        let issued = self.issued(nonce).ok_or(Rejection::Stale)?;
+        let now = now_millis();
+        let lifetime = self.lifetime.as_millis() as u64;
+        if issued > now || now - issued >= lifetime {
+            return Err(Rejection::Stale);
+        }
+
+        let mut state = self.counts.lock().unwrap();
+        if state.forgotten_until.is_some_and(|until| issued <= until) {
+            return Err(Rejection::Stale);
+        }
+        if let Some(used) = state.counts.get_mut(nonce) {
+            if count <= used.last_count {
+                return Err(Rejection::Stale);
+            }
+            used.last_count = count;
+            return Ok(());
+        }
+
+        if state.counts.len() >= MAX_NONCES {
+            state.counts.retain(|_, used| now.saturating_sub(used.issued) < lifetime);
+        }
+        if state.counts.len() >= MAX_NONCES {
+            // 丢弃最早签发的记录，同时拒绝不晚于它签发的 nonce，避免其 nonce-count 被重放
+            let oldest = state
+                .counts
+                .iter()
+                .min_by_key(|(_, used)| used.issued)
+                .map(|(nonce, used)| (nonce.clone(), used.issued));
+            if let Some((oldest, oldest_issued)) = oldest {
+                state.counts.remove(&oldest);
+                state.forgotten_until = state.forgotten_until.max(Some(oldest_issued));
+            }
+            if state.forgotten_until.is_some_and(|until| issued <= until) {
+                return Err(Rejection::Stale);
+            }
+        }
+        state.counts.insert(
+            nonce.to_string(),
+            NonceCount {
+                issued,
+                last_count: count,
+            },
+        );
+        Ok(())
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// RFC 7616 Digest 认证，只支持 `qop=auth`
+pub struct DigestAuthenticator {
+    credentials: Arc<DigestCredentials>,
+    /// 用于查找用户的其他属性，例如是否为管理员
+    users: Arc<UserDatabase>,
+    realm: String,
+    nonces: NonceStore,
+}
+
+impl DigestAuthenticator {
+    pub fn new(
+        credentials: Arc<DigestCredentials>,
+        users: Arc<UserDatabase>,
+        realm: &str,
+        nonce_lifetime: Duration,
+    ) -> Self {
+        Self {
+            credentials,
+            users,
+            realm: realm.to_string(),
+            nonces: NonceStore::new(nonce_lifetime),
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for DigestAuthenticator {
+    fn scheme(&self) -> &'static str {
+        "Digest"
+    }
+
+    fn challenges(&self, stale: bool) -> Vec<String> {
+        self.credentials
+            .algorithms()
+            .into_iter()
+            .map(|algorithm| {
+                let mut challenge = format!(
+                    "Digest realm={}, qop=\"auth\", algorithm={}, nonce=\"{}\", charset=UTF-8",
+                    quote(&self.realm),
+                    algorithm.name(),
+                    self.nonces.issue()
+                );
+                if stale {
+                    challenge.push_str(", stale=true");
+                }
+                challenge
+            })
+            .collect()
+    }
+
+    async fn authenticate(&self, method: &Method, uri: &Uri, credentials: &str) -> Result<Principal, Rejection> {
+        let params = parse_params(credentials).ok_or(Rejection::Invalid)?;
+        let param = |name: &str| params.get(name).map(String::as_str).ok_or(Rejection::Invalid);
+
+        let algorithm = match params.get("algorithm") {
+            Some(name) => DigestAlgorithm::parse(name).ok_or(Rejection::Invalid)?,
+            None => DigestAlgorithm::Md5,
+        };
+        if param("realm")? != self.realm
+            || param("qop")? != "auth"
+            || params.get("userhash").is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            return Err(Rejection::Invalid);
+        }
+        let digest_uri = param("uri")?;
+        if !same_request_target(digest_uri, uri) {
+            return Err(Rejection::Invalid);
+        }
+        let name = match params.get("username") {
+            Some(name) => name.clone(),
+            None => decode_ext_value(param("username*")?).ok_or(Rejection::Invalid)?,
+        };
+        let nonce = param("nonce")?;
+        let nc = param("nc")?;
+        let count = u32::from_str_radix(nc, 16).map_err(|_| Rejection::Invalid)?;
+        let cnonce = param("cnonce")?;
+
+        let ha1 = self.credentials.get(&name, algorithm).ok_or(Rejection::Invalid)?;
+        let expected = request_digest(algorithm, ha1, nonce, nc, cnonce, method, digest_uri);
+        let response = param("response")?.to_ascii_lowercase();
+        if !bool::from(expected.as_bytes().ct_eq(response.as_bytes())) {
+            return Err(Rejection::Invalid);
+        }
+
+        self.nonces.use_count(nonce, count)?;
+        let admin = self.users.get(&name).is_some_and(|entry| entry.admin);
+        Ok(Principal {
+            name,
+            admin,
+            access: Access::default(),
+        })
+    }
+}
+
+/// `qop=auth` 时的 response = H(HA1:nonce:nc:cnonce:auth:H(method:uri))
+fn request_digest(
+    algorithm: DigestAlgorithm,
+    ha1: &str,
+    nonce: &str,
+    nc: &str,
+    cnonce: &str,
+    method: &Method,
+    digest_uri: &str,
+) -> String {
+    let ha2 = algorithm.hash(&format!("{}:{}", method, digest_uri));
+    algorithm.hash(&format!("{}:{}:{}:{}:auth:{}", ha1, nonce, nc, cnonce, ha2))
+}
+
+/// 解析 `name=token` 或 `name="quoted"` 组成的逗号分隔列表，参数名转为小写
+fn parse_params(input: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('=')?;
+        let name = name.trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next()? {
+                        (_, '\\') => value.push(chars.next()?.1),
+                        (index, '"') => break index,
+                        (_, c) => value.push(c),
+                    }
+                };
+                (value, &quoted[end + 1..])
+            }
+            None => {
+                let end = after.find(',').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        params.insert(name, value);
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+    Some(params)
+}
+
+/// 摘要中的 `uri` 可以是请求行中的 origin-form 或完整的 URL
+fn same_request_target(digest_uri: &str, uri: &Uri) -> bool {
+    let target = match digest_uri.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |index| &rest[index..]),
+        None => digest_uri,
+    };
+    let request_target = uri.path_and_query().map_or("/", |target| target.as_str());
+    target == request_target
+}
+
+/// 解码 RFC 8187 的扩展参数值，只支持 UTF-8，例如 `UTF-8''J%C3%A4ger`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    if !charset.eq_ignore_ascii_case("UTF-8") {
+        return None;
+    }
+    percent_decode_str(encoded).decode_utf8().ok().map(|value| value.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REALM: &str = "http-auth@example.org";
+    const URI: &str = "/dir/index.html";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn ha1(algorithm: DigestAlgorithm) -> String {
+        algorithm.hash(&format!("Mufasa:{}:Circle of Life", REALM))
+    }
+
+    fn authenticator(lifetime: Duration) -> DigestAuthenticator {
+        let mut credentials = DigestCredentials::new();
+        for algorithm in DigestAlgorithm::PREFERRED {
+            credentials.insert("Mufasa", algorithm, &ha1(algorithm));
+        }
+        DigestAuthenticator::new(Arc::new(credentials), Arc::new(UserDatabase::new()), REALM, lifetime)
+    }
+
+    /// 取出质询中的 nonce
+    fn nonce(authenticator: &DigestAuthenticator) -> String {
+        let challenge = authenticator.challenges(false).remove(0);
+        parse_params(challenge.strip_prefix("Digest ").unwrap()).unwrap()["nonce"].clone()
+    }
+
+    fn credentials(algorithm: DigestAlgorithm, nonce: &str, nc: u32) -> String {
+        let nc = format!("{:08x}", nc);
+        let response = request_digest(algorithm, &ha1(algorithm), nonce, &nc, CNONCE, &Method::GET, URI);
+        format!(
+            "username=\"Mufasa\", realm=\"{}\", uri=\"{}\", algorithm={}, nonce=\"{}\", \
+             nc={}, cnonce=\"{}\", qop=auth, response=\"{}\"",
+            REALM,
+            URI,
+            algorithm.name(),
+            nonce,
+            nc,
+            CNONCE,
+            response
+        )
+    }
+
+    async fn authenticate(authenticator: &DigestAuthenticator, credentials: &str) -> Result<Principal, Rejection> {
+        let uri: Uri = URI.parse().unwrap();
+        authenticator.authenticate(&Method::GET, &uri, credentials).await
+    }
+
+    /// RFC 7616 第 3.9.1 节的示例
+    #[test]
+    fn rfc7616_vectors() {
+        let nonce = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+        let response = |algorithm| {
+            request_digest(algorithm, &ha1(algorithm), nonce, "00000001", CNONCE, &Method::GET, URI)
+        };
+        assert_eq!(response(DigestAlgorithm::Md5), "8ca523f5e9506fed4657c9700eebdbec");
+        assert_eq!(
+            response(DigestAlgorithm::Sha256),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_issued_nonce() {
+        let authenticator = authenticator(Duration::from_secs(60));
+        for algorithm in DigestAlgorithm::PREFERRED {
+            let nonce = nonce(&authenticator);
+            let principal = authenticate(&authenticator, &credentials(algorithm, &nonce, 1)).await.unwrap();
+            assert_eq!(principal.name, "Mufasa");
+            assert!(!principal.admin);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_response() {
+        let authenticator = authenticator(Duration::from_secs(60));
+        let nonce = nonce(&authenticator);
+        let credentials = credentials(DigestAlgorithm::Md5, &nonce, 1).replace("Mufasa", "Simba");
+        assert_eq!(authenticate(&authenticator, &credentials).await, Err(Rejection::Invalid));
+    }
+
+    #[tokio::test]
+    async fn nonce_count_replay_is_stale() {
+        let authenticator = authenticator(Duration::from_secs(60));
+        let nonce = nonce(&authenticator);
+        let first = credentials(DigestAlgorithm::Sha256, &nonce, 1);
+        assert!(authenticate(&authenticator, &first).await.is_ok());
+        assert_eq!(authenticate(&authenticator, &first).await, Err(Rejection::Stale));
+        let third = credentials(DigestAlgorithm::Sha256, &nonce, 3);
+        assert!(authenticate(&authenticator, &third).await.is_ok());
+        // 乱序的 nonce-count 同样视为重放
+        let second = credentials(DigestAlgorithm::Sha256, &nonce, 2);
+        assert_eq!(authenticate(&authenticator, &second).await, Err(Rejection::Stale));
+    }
+
+    #[tokio::test]
+    async fn expired_nonce_is_stale() {
+        let authenticator = authenticator(Duration::from_millis(20));
+        let nonce = nonce(&authenticator);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let credentials = credentials(DigestAlgorithm::Md5, &nonce, 1);
+        assert_eq!(authenticate(&authenticator, &credentials).await, Err(Rejection::Stale));
+    }
+
+    #[tokio::test]
+    async fn foreign_nonce_is_stale() {
+        let authenticator = authenticator(Duration::from_secs(60));
+        // 其他进程（密钥不同）签发的 nonce 和伪造的 nonce
+        let other = nonce(&self::authenticator(Duration::from_secs(60)));
+        for nonce in [other.as_str(), "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v"] {
+            let credentials = credentials(DigestAlgorithm::Md5, nonce, 1);
+            assert_eq!(authenticate(&authenticator, &credentials).await, Err(Rejection::Stale));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, Method, Request, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::error::WebDavError;
+
+mod basic;
+mod bearer;
+mod digest;
+mod users;
+
+pub use basic::BasicAuthenticator;
+pub use bearer::{BearerAuthenticator, JwtVerifier, TokenDatabase};
+pub use digest::{DigestAlgorithm, DigestAuthenticator, DigestCredentials};
+pub use users::{is_supported_hash, verify_password, UserDatabase, UserEntry};
+
+/// 通过认证的用户，认证中间件将它放入请求的 extensions 中供处理器使用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub name: String,
+    pub admin: bool,
+    pub access: Access,
+}
+
+/// 令牌的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    #[default]
+    Read,
+    ReadWrite,
+}
+
+/// 凭据允许访问的资源，密码认证的用户不受限制
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Access {
+    /// 只能访问这个 URL 路径（含挂载点）之下的资源，按完整的路径段匹配
+    pub path_prefix: Option<PathBuf>,
+    pub read_only: bool,
+}
+
+impl Access {
+    /// 由令牌中的路径前缀和权限范围创建，前缀必须是不含 `.` 和 `..` 的绝对路径
+    pub fn restricted(path_prefix: Option<&str>, scope: Scope) -> Result<Self, String> {
+        let path_prefix = match path_prefix {
+            Some(prefix) => {
+                let prefix = Path::new(prefix);
+                let valid = prefix.has_root()
+                    && prefix
+                        .components()
+                        .all(|component| matches!(component, Component::RootDir | Component::Normal(_)));
+                if !valid {
+                    return Err(format!("invalid path prefix {:?}", prefix));
+                }
+                Some(prefix.to_path_buf())
+            }
+            None => None,
+        };
+        Ok(Self {
+            path_prefix,
+            read_only: scope == Scope::Read,
+        })
+    }
+
+    /// 是否允许访问 `url_path`，`write` 表示修改资源
+    pub fn allows(&self, url_path: &Path, write: bool) -> bool {
+        if write && self.read_only {
+            return false;
+        }
+        self.path_prefix
+            .as_ref()
+            .is_none_or(|prefix| url_path.starts_with(prefix))
+    }
+}
+
+/// 一种 HTTP 认证方案
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    /// `Authorization` 头中的方案名，例如 `Basic`，比较时忽略大小写
+    fn scheme(&self) -> &'static str;
+
+    /// 401 响应中 `WWW-Authenticate` 头的值，每个值是一个质询。
+    ///
+    /// `stale` 表示客户端的凭据有效但 nonce 已过期。
+    fn challenges(&self, stale: bool) -> Vec<String>;
+
+    /// 校验 `Authorization` 头中方案名之后的凭据
+    async fn authenticate(&self, method: &Method, uri: &Uri, credentials: &str) -> Result<Principal, Rejection>;
+}
+
+/// 凭据未通过校验的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Invalid,
+    /// 凭据正确但 nonce 已过期，客户端可以用新的 nonce 重试而不必重新询问密码
+    Stale,
+}
+
+/// 按配置的认证方案校验请求
+#[derive(Clone, Default)]
+pub struct Authentication {
+    authenticators: Vec<Arc<dyn Authenticator>>,
+}
+
+impl Authentication {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_authenticator<A: Authenticator + 'static>(mut self, authenticator: A) -> Self {
+        self.authenticators.push(Arc::new(authenticator));
+        self
+    }
+
+    /// 没有配置任何认证方案时不需要认证
+    pub fn is_empty(&self) -> bool {
+        self.authenticators.is_empty()
+    }
+
+    /// 校验请求的凭据，缺少或无效时返回带所有方案质询的 401
+    pub async fn authenticate(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> Result<Principal, WebDavError> {
+        let credentials = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                let value = value.trim();
+                value.split_once(' ').unwrap_or((value, ""))
+            });
+
+        let mut stale_scheme = None;
+        if let Some((scheme, credentials)) = credentials {
+            let authenticator = self
+                .authenticators
+                .iter()
+                .find(|authenticator| authenticator.scheme().eq_ignore_ascii_case(scheme));
+            if let Some(authenticator) = authenticator {
+                match authenticator.authenticate(method, uri, credentials).await {
+                    Ok(principal) => return Ok(principal),
+                    Err(Rejection::Stale) => stale_scheme = Some(authenticator.scheme()),
+                    Err(Rejection::Invalid) => warn!(scheme = scheme, uri = %uri, "Authentication failed"),
+                }
+            }
+        }
+
+        Err(WebDavError::Unauthorized(
+            self.authenticators
+                .iter()
+                .flat_map(|authenticator| authenticator.challenges(stale_scheme == Some(authenticator.scheme())))
+                .collect(),
+        ))
+    }
+}
+
+/// axum 中间件：认证成功时将 [`Principal`] 放入请求的 extensions，否则返回 401。
+///
+/// OPTIONS 不需要认证，部分客户端会在发送凭据之前先用它探测服务器能力。
+pub async fn require_auth(
+    State(auth): State<Arc<Authentication>>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    // 避免凭据出现在请求日志中
+    if let Some(value) = request.headers_mut().get_mut(header::AUTHORIZATION) {
+        value.set_sensitive(true);
+    }
+    if auth.is_empty() || request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+    match auth.authenticate(request.method(), request.uri(), request.headers()).await {
+        Ok(principal) => {
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// 生成质询参数中的 quoted-string
+pub(crate) fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 生成进程内使用的随机密钥，重启后旧密钥签发的值全部失效
+pub(crate) fn random_key() -> [u8; 32] {
+    let mut key = [0; 32];
+    getrandom::getrandom(&mut key).expect("failed to read random bytes");
+    key
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+
+/// 用户数据库中的一条记录
+#[derive(Debug, Clone)]
+pub struct UserEntry {
+    /// bcrypt 或 argon2 格式的密码哈希
+    pub password_hash: String,
+    pub admin: bool,
+}
+
+/// 用户名到密码哈希的映射
+#[derive(Debug, Clone, Default)]
+pub struct UserDatabase {
+    users: HashMap<String, UserEntry>,
+}
+
+impl UserDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加用户，同名用户会被替换
+    pub fn insert(&mut self, name: &str, password_hash: &str, admin: bool) {
+        self.users.insert(
+            name.to_string(),
+            UserEntry {
+                password_hash: password_hash.to_string(),
+                admin,
+            },
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UserEntry> {
+        self.users.get(name)
+    }
+
+    /// 任意一个用户的密码哈希，用于在用户不存在时做一次等价的验证
+    pub fn any_hash(&self) -> Option<&str> {
+        self.users.values().next().map(|entry| entry.password_hash.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    /// 读取 htpasswd 格式的用户文件：每行 `name:hash`，忽略空行和 `#` 开头的注释。
+    ///
+    /// 文件中的用户都不是管理员。
+    pub fn load_htpasswd<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, message))
+            };
+            let (name, hash) = line.split_once(':').ok_or_else(|| invalid("expected name:hash"))?;
+            if name.is_empty() {
+                return Err(invalid("empty user name"));
+            }
+            if !is_supported_hash(hash) {
+                return Err(invalid("unsupported password hash, expected bcrypt or argon2"));
+            }
+            self.insert(name, hash, false);
+        }
+        Ok(())
+    }
+}
+
+/// 是否为支持的密码哈希格式
+pub fn is_supported_hash(hash: &str) -> bool {
+    is_bcrypt(hash) || PasswordHash::new(hash).is_ok_and(|hash| hash.algorithm.as_str().starts_with("argon2"))
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+}
+
+/// 校验密码，哈希格式无效时视为不匹配。bcrypt 和 argon2 都很慢，应在阻塞线程中调用
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    if is_bcrypt(hash) {
+        return bcrypt::verify(password, hash).unwrap_or(false);
+    }
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
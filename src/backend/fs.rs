@@ -1,35 +1,511 @@
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::{ErrorKind, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 use async_trait::async_trait;
-use bytes::Bytes;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tokio_util::io::{ReaderStream, StreamReader};
 use uuid::Uuid;
 use std::pin::Pin;
 use std::future::Future;
 
-use super::{Backend, ResourceInfo, ResourceMetadata};
+use super::{Backend, ByteStream, DeadProperty, Depth, MemberStatus, PropertyUpdate, ResourceInfo, ResourceMetadata};
 use crate::error::WebDavError;
 
+/// 每个目录下保存死属性的隐藏文件，键为成员名，目录自身的属性使用 "."
+pub const PROPS_FILE: &str = ".webdav-props.json";
+
+const SELF_KEY: &str = ".";
+
+/// 客户端可以通过 PROPPATCH 保存这些 DAV: 属性来设置单个文件的 MIME 类型和语言
+const DAV_NAMESPACE: &str = "DAV:";
+const CONTENT_TYPE_PROPERTY: &str = "getcontenttype";
+const CONTENT_LANGUAGE_PROPERTY: &str = "getcontentlanguage";
+
+/// 内容嗅探时读取的文件头长度
+const SNIFF_LEN: u64 = 8192;
+
+/// 写入过程中使用的临时文件前缀，重命名到目标路径前对外不可见
+const TEMP_PREFIX: &str = ".webdav-tmp-";
+
+/// 内容哈希缓存的最大项数
+const HASH_CACHE_CAPACITY: usize = 16 * 1024;
+
+type PropertyStore = BTreeMap<String, Vec<DeadProperty>>;
+
+/// 文件 ETag 的生成方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EtagMode {
+    /// 由 inode、大小和纳秒级修改时间生成，无需读取文件内容
+    #[default]
+    Metadata,
+    /// 使用文件内容的 SHA-256，内容不变时 ETag 不变（例如文件被原样复制或 touch）
+    ContentHash,
+}
+
+/// 写入文件时的持久化级别，级别越高崩溃后越安全，吞吐量越低
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Durability {
+    /// 不调用 fsync，由操作系统决定何时落盘；重命名仍保证其他客户端看不到写了一半的文件
+    Relaxed,
+    /// 重命名前对文件调用 fsync
+    #[default]
+    File,
+    /// 同时对文件和所在目录调用 fsync，已返回成功的写入在断电后也不会丢失
+    Full,
+}
+
+/// 存储目录中符号链接的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// 路径中出现任何符号链接都拒绝访问（403）
+    Deny,
+    /// 只跟随目标仍位于存储根目录内的符号链接
+    #[default]
+    FollowWithinRoot,
+    /// 跟随所有符号链接，可以访问根目录之外的内容
+    FollowAll,
+}
+
 #[derive(Clone)]
 pub struct FileSystemBackend {
     root: PathBuf,
+    props_lock: Arc<Mutex<()>>,
+    etag_mode: EtagMode,
+    durability: Durability,
+    symlink_policy: SymlinkPolicy,
+    /// 扩展名未知时是否根据文件头的魔数判断 MIME 类型
+    sniff_content_type: bool,
+    /// 扩展名（小写，不含点）到 MIME 类型的映射，优先于内置的映射表
+    mime_overrides: Arc<HashMap<String, String>>,
+    /// 内容哈希缓存：路径 -> (元数据 ETag, 内容 ETag)，元数据变化时重新计算。
+    ///
+    /// 最多保存 [`HASH_CACHE_CAPACITY`] 项，资源被删除、移走或覆盖时移除对应的项。
+    hash_cache: Arc<std::sync::Mutex<HashMap<PathBuf, (String, String)>>>,
 }
 
 impl FileSystemBackend {
     pub fn new<P: AsRef<Path>>(root: P) -> Self {
         Self {
             root: root.as_ref().to_path_buf(),
+            props_lock: Arc::new(Mutex::new(())),
+            etag_mode: EtagMode::default(),
+            durability: Durability::default(),
+            symlink_policy: SymlinkPolicy::default(),
+            sniff_content_type: false,
+            mime_overrides: Arc::new(HashMap::new()),
+            hash_cache: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_etag_mode(mut self, mode: EtagMode) -> Self {
+        self.etag_mode = mode;
+        self
+    }
+
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    pub fn with_content_sniffing(mut self, enabled: bool) -> Self {
+        self.sniff_content_type = enabled;
+        self
+    }
+
+    /// 设置扩展名到 MIME 类型的映射，扩展名不区分大小写，可以带前导的 `.`
+    pub fn with_mime_overrides(mut self, overrides: HashMap<String, String>) -> Self {
+        self.mime_overrides = Arc::new(
+            overrides
+                .into_iter()
+                .map(|(ext, mime)| (ext.trim_start_matches('.').to_ascii_lowercase(), mime))
+                .collect(),
+        );
+        self
+    }
+
+    /// 删除上次运行遗留的临时文件（进程崩溃或连接中断时产生），返回删除的数量。
+    ///
+    /// 应在启动时、开始处理请求之前调用。
+    pub async fn cleanup_temp_files(&self) -> Result<usize, WebDavError> {
+        let mut removed = 0;
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut read_dir = match fs::read_dir(&dir).await {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = read_dir.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                if file_type.is_dir() {
+                    pending.push(entry.path());
+                } else if entry.file_name().to_string_lossy().starts_with(TEMP_PREFIX) {
+                    fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
         }
+        Ok(removed)
     }
 
-    fn resolve_path(&self, path: &PathBuf) -> PathBuf {
-        if path.as_os_str().is_empty() {
-            self.root.clone()
+    /// `props` 为资源已保存的死属性，用于读取客户端覆盖的 MIME 类型，并随元数据一起返回
+    async fn resource_metadata(
+        &self,
+        path: &Path,
+        full_path: &Path,
+        metadata: &std::fs::Metadata,
+        props: Vec<DeadProperty>,
+    ) -> Result<ResourceMetadata, WebDavError> {
+        let (etag, content_type) = if metadata.is_dir() {
+            (directory_etag(full_path).await?, None)
         } else {
-            self.root.join(path.strip_prefix("/").unwrap_or(path))
+            let content_type = match stored_text(&props, CONTENT_TYPE_PROPERTY) {
+                Some(content_type) => content_type,
+                None => self.detect_content_type(full_path).await,
+            };
+            (self.file_etag(full_path, metadata).await?, Some(content_type))
+        };
+
+        Ok(ResourceMetadata {
+            path: path.to_path_buf(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified()?.into(),
+            created: metadata.created().ok().map(|t| t.into()),
+            etag,
+            content_type,
+            content_language: stored_text(&props, CONTENT_LANGUAGE_PROPERTY),
+            file_id: file_id(metadata),
+            dead_properties: Some(props),
+        })
+    }
+
+    /// 依次按照自定义映射、内置扩展名映射和（启用时）文件头魔数判断 MIME 类型
+    async fn detect_content_type(&self, full_path: &Path) -> String {
+        let extension = full_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        if let Some(mime) = extension.as_ref().and_then(|ext| self.mime_overrides.get(ext)) {
+            return mime.clone();
+        }
+        if let Some(mime) = mime_guess::from_path(full_path).first_raw() {
+            return mime.to_string();
+        }
+        if self.sniff_content_type {
+            if let Some(mime) = sniff_content_type(full_path).await {
+                return mime;
+            }
+        }
+        "application/octet-stream".to_string()
+    }
+
+    async fn file_etag(&self, full_path: &Path, metadata: &std::fs::Metadata) -> Result<String, WebDavError> {
+        let stat_etag = metadata_etag(metadata);
+        if self.etag_mode == EtagMode::Metadata {
+            return Ok(stat_etag);
+        }
+
+        if let Some((key, etag)) = self.hash_cache.lock().unwrap().get(full_path) {
+            if *key == stat_etag {
+                return Ok(etag.clone());
+            }
+        }
+
+        let mut file = fs::File::open(full_path).await?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        let etag = format!("\"{:x}\"", hasher.finalize());
+
+        let mut cache = self.hash_cache.lock().unwrap();
+        if cache.len() >= HASH_CACHE_CAPACITY && !cache.contains_key(full_path) {
+            // 已满时丢弃任意一项，被丢弃的文件下次访问时重新计算
+            let evicted = cache.keys().next().cloned();
+            if let Some(evicted) = evicted {
+                cache.remove(&evicted);
+            }
+        }
+        cache.insert(full_path.to_path_buf(), (stat_etag, etag.clone()));
+        Ok(etag)
+    }
+
+    /// 移除 `full_path` 及其之下所有文件的内容哈希
+    fn forget_hashes(&self, full_path: &Path) {
+        if self.etag_mode == EtagMode::Metadata {
+            return;
+        }
+        self.hash_cache
+            .lock()
+            .unwrap()
+            .retain(|path, _| !path.starts_with(full_path));
+    }
+
+    /// 将后端路径映射到存储根目录下的实际路径。
+    ///
+    /// 拒绝 `..`、NUL、内部文件名和平台保留的文件名，并按照符号链接策略检查路径上已存在的每一级。
+    async fn resolve_path(&self, path: &Path) -> Result<PathBuf, WebDavError> {
+        let mut full_path = self.root.clone();
+        for component in path.components() {
+            match component {
+                Component::RootDir | Component::CurDir => {}
+                Component::Normal(name) if name.as_encoded_bytes().contains(&0) => {
+                    return Err(WebDavError::InvalidInput(format!(
+                        "Invalid path: {}",
+                        path.display()
+                    )));
+                }
+                Component::Normal(name) if aliases_internal(name) => {
+                    return Err(WebDavError::PermissionDenied(path.to_path_buf()));
+                }
+                Component::Normal(name) if is_reserved(name) => {
+                    return Err(WebDavError::InvalidInput(format!(
+                        "Reserved file name: {}",
+                        path.display()
+                    )));
+                }
+                Component::Normal(name) => full_path.push(name),
+                Component::ParentDir | Component::Prefix(_) => {
+                    return Err(WebDavError::InvalidInput(format!(
+                        "Invalid path: {}",
+                        path.display()
+                    )));
+                }
+            }
+        }
+
+        if self.symlink_policy != SymlinkPolicy::FollowAll {
+            let mut current = self.root.clone();
+            for name in full_path.strip_prefix(&self.root).unwrap_or(Path::new("")).iter() {
+                current.push(name);
+                match fs::symlink_metadata(&current).await {
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        if !self.symlink_allowed(&current).await {
+                            return Err(WebDavError::PermissionDenied(path.to_path_buf()));
+                        }
+                    }
+                    Ok(_) => {}
+                    // 其余部分尚不存在，不会经过符号链接
+                    Err(_) => break,
+                }
+            }
+        }
+        Ok(full_path)
+    }
+
+    /// 按照符号链接策略判断是否可以跟随 `link`
+    async fn symlink_allowed(&self, link: &Path) -> bool {
+        match self.symlink_policy {
+            SymlinkPolicy::Deny => false,
+            SymlinkPolicy::FollowAll => true,
+            SymlinkPolicy::FollowWithinRoot => {
+                // 悬空链接的目标可能在之后被创建到根目录之外，同样拒绝
+                match (fs::canonicalize(&self.root).await, fs::canonicalize(link).await) {
+                    (Ok(root), Ok(target)) => target.starts_with(root),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /// 递归复制目录，`path` 为目标在后端中的路径，复制失败的成员记录到 `failures`。
+    ///
+    /// `visited` 记录已经复制过和新建的目录，符号链接再次指向它们时报告 508 而不是无限复制。
+    fn copy_dir_all<'a>(
+        &'a self,
+        src: &'a Path,
+        dst: &'a Path,
+        path: &'a Path,
+        failures: &'a mut Vec<MemberStatus>,
+        visited: &'a mut HashSet<(u64, u64)>,
+    ) -> Pin<Box<dyn Future<Output = std::io::Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            fs::create_dir(&dst).await?;
+            for dir in [src, dst] {
+                if let Some(id) = file_id(&fs::metadata(dir).await?) {
+                    visited.insert(id);
+                }
+            }
+            let mut read_dir = fs::read_dir(src).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                let name = entry.file_name();
+                if name.to_string_lossy().starts_with(TEMP_PREFIX) {
+                    continue;
+                }
+                let member = path.join(&name);
+                let src_path = entry.path();
+                let dst_path = dst.join(&name);
+                if entry.file_type().await?.is_symlink() && !self.symlink_allowed(&src_path).await {
+                    failures.push(MemberStatus {
+                        path: member,
+                        is_dir: false,
+                        status: http::StatusCode::FORBIDDEN,
+                    });
+                    continue;
+                }
+                let metadata = fs::metadata(&src_path).await;
+                let is_dir = metadata.as_ref().is_ok_and(|metadata| metadata.is_dir());
+                let result = match metadata {
+                    Ok(metadata) if metadata.is_dir() && file_id(&metadata).is_some_and(|id| visited.contains(&id)) => {
+                        failures.push(MemberStatus {
+                            path: member,
+                            is_dir: true,
+                            status: http::StatusCode::LOOP_DETECTED,
+                        });
+                        continue;
+                    }
+                    Ok(metadata) if metadata.is_dir() => {
+                        self.copy_dir_all(&src_path, &dst_path, &member, failures, visited).await
+                    }
+                    Ok(_) => fs::copy(&src_path, &dst_path).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    let (member, is_dir) = if is_internal(&name) {
+                        (path.to_path_buf(), true)
+                    } else {
+                        (member, is_dir)
+                    };
+                    failures.push(MemberStatus::from_io(member, is_dir, &e));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// `rename` 无法跨文件系统时先复制再删除源，复制不完整时保留源
+    async fn move_across_devices(
+        &self,
+        src_path: &Path,
+        dst_path: &Path,
+        from: &Path,
+        to: &Path,
+    ) -> Result<(), WebDavError> {
+        if !fs::metadata(src_path).await?.is_dir() {
+            fs::copy(src_path, dst_path).await?;
+            fs::remove_file(src_path).await?;
+            return Ok(());
+        }
+
+        let mut failures = Vec::new();
+        self.copy_dir_all(src_path, dst_path, to, &mut failures, &mut HashSet::new()).await?;
+        if failures.is_empty() {
+            remove_dir_tree(src_path, from, &mut failures).await?;
+        }
+        if !failures.is_empty() {
+            return Err(WebDavError::Multistatus(failures));
+        }
+        Ok(())
+    }
+
+    /// COPY/MOVE 的目标不能已存在（覆盖由调用方先删除），且父集合必须存在
+    async fn check_destination(&self, to: &Path, dst_path: &Path) -> Result<(), WebDavError> {
+        if fs::symlink_metadata(dst_path).await.is_ok() {
+            return Err(WebDavError::AlreadyExists(to.to_path_buf()));
+        }
+        let parent_is_dir = match dst_path.parent() {
+            Some(parent) => fs::metadata(parent).await.is_ok_and(|m| m.is_dir()),
+            None => false,
+        };
+        if !parent_is_dir {
+            return Err(WebDavError::Conflict(format!(
+                "Parent collection of {} does not exist",
+                to.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 返回保存 `full_path` 死属性的文件及其中的键。
+    ///
+    /// 目录的属性保存在目录自身的属性文件中，随目录一起被复制、移动和删除；
+    /// 文件的属性保存在父目录的属性文件中，需要单独维护。
+    async fn props_location(&self, full_path: &Path) -> Result<(PathBuf, String), WebDavError> {
+        let metadata = fs::metadata(full_path).await?;
+        if metadata.is_dir() {
+            return Ok((full_path.join(PROPS_FILE), SELF_KEY.to_string()));
+        }
+        let parent = full_path.parent().unwrap_or(&self.root);
+        let name = full_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok((parent.join(PROPS_FILE), name))
+    }
+
+    async fn load_props(file: &Path) -> Result<PropertyStore, WebDavError> {
+        match fs::read(file).await {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| WebDavError::Internal(format!("Corrupt property store {}: {}", file.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PropertyStore::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 先写临时文件再重命名，保证属性文件总是完整的
+    async fn save_props(file: &Path, store: &PropertyStore) -> Result<(), WebDavError> {
+        if store.is_empty() {
+            return match fs::remove_file(file).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+        let content = serde_json::to_vec_pretty(store)
+            .map_err(|e| WebDavError::Internal(e.to_string()))?;
+        let tmp = TempFile::new(file.parent().unwrap_or(Path::new(".")));
+        fs::write(tmp.path(), content).await?;
+        fs::rename(tmp.path(), file).await?;
+        tmp.persist();
+        Ok(())
+    }
+
+    /// 取出文件的死属性并从属性文件中移除；目录的属性随目录本身移动，无需处理
+    async fn take_file_props(&self, full_path: &Path) -> Result<Option<Vec<DeadProperty>>, WebDavError> {
+        if fs::metadata(full_path).await?.is_dir() {
+            return Ok(None);
         }
+        let (file, key) = self.props_location(full_path).await?;
+        let mut store = Self::load_props(&file).await?;
+        let props = store.remove(&key);
+        if props.is_some() {
+            Self::save_props(&file, &store).await?;
+        }
+        Ok(props)
     }
-    async fn exists(&self, path: &PathBuf) -> Result<bool, WebDavError> {
-        Ok(fs::metadata(path).await.is_ok())
+
+    /// 将属性写入文件 `full_path` 的属性文件，覆盖原有的属性
+    async fn put_file_props(&self, full_path: &Path, props: Option<Vec<DeadProperty>>) -> Result<(), WebDavError> {
+        let (file, key) = self.props_location(full_path).await?;
+        let mut store = Self::load_props(&file).await?;
+        match props {
+            Some(props) => {
+                store.insert(key, props);
+            }
+            None if store.remove(&key).is_none() => return Ok(()),
+            None => {}
+        }
+        Self::save_props(&file, &store).await
     }
 }
 
@@ -37,35 +513,43 @@ impl FileSystemBackend {
 impl Backend for FileSystemBackend {
     
 
-    async fn get_resource(&self, path: &PathBuf) -> Result<ResourceInfo, WebDavError> {
-        let full_path = self.resolve_path(path);
+    async fn get_resource(&self, path: &Path) -> Result<ResourceInfo, WebDavError> {
+        let full_path = self.resolve_path(path).await?;
         let metadata = fs::metadata(&full_path)
             .await
-            .map_err(|_| WebDavError::NotFound(path.clone()))?;
+            .map_err(|_| WebDavError::NotFound(path.to_path_buf()))?;
 
-        let resource_metadata = ResourceMetadata {
-            path: path.clone(),
-            is_dir: metadata.is_dir(),
-            len: metadata.len(),
-            modified: metadata.modified()?.into(),
-            created: metadata.created().ok().map(|t| t.into()),
-            etag: format!("\"{:x}\"", Uuid::new_v4()),
-        };
+        // 目录自身和其中文件的死属性都保存在目录的属性文件中，只读取一次
+        let (file, key) = self.props_location(&full_path).await?;
+        let mut store = Self::load_props(&file).await?;
+        let props = store.remove(&key).unwrap_or_default();
+        let resource_metadata = self.resource_metadata(path, &full_path, &metadata, props).await?;
 
         let children = if metadata.is_dir() {
             let mut entries = Vec::new();
             let mut read_dir = fs::read_dir(&full_path).await?;
             while let Some(entry) = read_dir.next_entry().await? {
+                if is_internal(&entry.file_name()) {
+                    continue;
+                }
+                // 不允许跟随的符号链接和悬空链接不出现在列表中
+                if entry.file_type().await?.is_symlink() && !self.symlink_allowed(&entry.path()).await {
+                    continue;
+                }
+                let Ok(child_metadata) = fs::metadata(entry.path()).await else {
+                    continue;
+                };
                 let child_path = path.join(entry.file_name());
-                let child_metadata = entry.metadata().await?;
-                entries.push(ResourceMetadata {
-                    path: child_path,
-                    is_dir: child_metadata.is_dir(),
-                    len: child_metadata.len(),
-                    modified: child_metadata.modified()?.into(),
-                    created: child_metadata.created().ok().map(|t| t.into()),
-                    etag: format!("\"{:x}\"", Uuid::new_v4()),
-                });
+                // 子目录的死属性保存在子目录自身的属性文件中
+                let child_props = if child_metadata.is_dir() {
+                    Self::load_props(&entry.path().join(PROPS_FILE)).await?.remove(SELF_KEY)
+                } else {
+                    store.remove(entry.file_name().to_string_lossy().as_ref())
+                };
+                entries.push(
+                    self.resource_metadata(&child_path, &entry.path(), &child_metadata, child_props.unwrap_or_default())
+                        .await?,
+                );
             }
             Some(entries)
         } else {
@@ -78,90 +562,422 @@ impl Backend for FileSystemBackend {
         })
     }
 
-    async fn read_file(&self, path: &PathBuf) -> Result<Bytes, WebDavError> {
-        let full_path = self.resolve_path(path);
-        let content = fs::read(&full_path)
+    async fn read_file(&self, path: &Path, range: Option<Range<u64>>) -> Result<ByteStream, WebDavError> {
+        let full_path = self.resolve_path(path).await?;
+        let mut file = fs::File::open(&full_path)
             .await
-            .map_err(|_| WebDavError::NotFound(path.clone()))?;
-        Ok(Bytes::from(content))
+            .map_err(|_| WebDavError::NotFound(path.to_path_buf()))?;
+
+        match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                let len = range.end.saturating_sub(range.start);
+                Ok(Box::pin(ReaderStream::new(file.take(len))))
+            }
+            None => Ok(Box::pin(ReaderStream::new(file))),
+        }
     }
 
-    async fn write_file(&self, path: &PathBuf, content: Bytes) -> Result<(), WebDavError> {
-        let full_path = self.resolve_path(path);
-        if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent).await?;
+    async fn write_file(&self, path: &Path, content: ByteStream) -> Result<bool, WebDavError> {
+        let full_path = self.resolve_path(path).await?;
+        let existed = match fs::metadata(&full_path).await {
+            Ok(metadata) if metadata.is_dir() => {
+                return Err(WebDavError::MethodNotAllowed("Cannot PUT to a collection".to_string()));
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        };
+
+        let parent_is_dir = match full_path.parent() {
+            Some(parent) => fs::metadata(parent).await.is_ok_and(|m| m.is_dir()),
+            None => false,
+        };
+        if !parent_is_dir {
+            return Err(WebDavError::Conflict(format!(
+                "Parent collection of {} does not exist",
+                path.display()
+            )));
         }
-        if self.exists(&full_path).await? {
-            return Err(WebDavError::AlreadyExists(path.clone()));
+
+        // 先写入同目录下的临时文件，请求体完整接收后再原子地重命名到目标路径
+        let parent = full_path.parent().unwrap_or(&self.root);
+        let tmp = TempFile::new(parent);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(tmp.path())
+            .await?;
+        tokio::io::copy(&mut StreamReader::new(content), &mut file).await?;
+        file.flush().await?;
+        if self.durability != Durability::Relaxed {
+            file.sync_all().await?;
         }
-        fs::write(&full_path, content).await?;
-        Ok(())
+        drop(file);
+
+        fs::rename(tmp.path(), &full_path).await?;
+        tmp.persist();
+        self.forget_hashes(&full_path);
+        if self.durability == Durability::Full {
+            sync_dir(parent).await?;
+        }
+        Ok(!existed)
     }
 
-    async fn create_dir(&self, path: &PathBuf) -> Result<(), WebDavError> {
-        let full_path = self.resolve_path(path);
+    async fn create_dir(&self, path: &Path) -> Result<(), WebDavError> {
+        let full_path = self.resolve_path(path).await?;
         fs::create_dir_all(&full_path).await?;
         Ok(())
     }
 
-    async fn delete(&self, path: &PathBuf) -> Result<(), WebDavError> {
-        let full_path = self.resolve_path(path);
+    async fn delete(&self, path: &Path) -> Result<(), WebDavError> {
+        let full_path = self.resolve_path(path).await?;
         let metadata = fs::metadata(&full_path).await?;
+        self.forget_hashes(&full_path);
         if metadata.is_dir() {
-            fs::remove_dir_all(&full_path).await?;
+            let mut failures = Vec::new();
+            remove_dir_tree(&full_path, path, &mut failures).await?;
+            if !failures.is_empty() {
+                return Err(WebDavError::Multistatus(failures));
+            }
         } else {
+            let _guard = self.props_lock.lock().await;
+            self.take_file_props(&full_path).await?;
             fs::remove_file(&full_path).await?;
         }
         Ok(())
     }
 
-    async fn copy(&self, from: &PathBuf, to: &PathBuf) -> Result<(), WebDavError> {
-        let src_path = self.resolve_path(from);
-        let dst_path = self.resolve_path(to);
-        
-        if let Some(parent) = dst_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
+    async fn copy(&self, from: &Path, to: &Path, depth: Depth) -> Result<(), WebDavError> {
+        let src_path = self.resolve_path(from).await?;
+        let dst_path = self.resolve_path(to).await?;
+        let metadata = fs::metadata(&src_path)
+            .await
+            .map_err(|_| WebDavError::NotFound(from.to_path_buf()))?;
+        self.check_destination(to, &dst_path).await?;
 
-        let metadata = fs::metadata(&src_path).await?;
-        if metadata.is_dir() {
-            copy_dir_all(&src_path, &dst_path).await?;
+        if metadata.is_dir() && depth == Depth::Zero {
+            // 只复制集合本身及其属性，不包含成员
+            fs::create_dir(&dst_path).await?;
+            let _guard = self.props_lock.lock().await;
+            let mut store = Self::load_props(&src_path.join(PROPS_FILE)).await?;
+            store.retain(|key, _| key == SELF_KEY);
+            Self::save_props(&dst_path.join(PROPS_FILE), &store).await?;
+        } else if metadata.is_dir() {
+            let mut failures = Vec::new();
+            self.copy_dir_all(&src_path, &dst_path, to, &mut failures, &mut HashSet::new()).await?;
+            if !failures.is_empty() {
+                return Err(WebDavError::Multistatus(failures));
+            }
         } else {
             fs::copy(&src_path, &dst_path).await?;
+            let _guard = self.props_lock.lock().await;
+            let (file, key) = self.props_location(&src_path).await?;
+            let props = Self::load_props(&file).await?.remove(&key);
+            self.put_file_props(&dst_path, props).await?;
         }
         Ok(())
     }
 
-    async fn move_resource(&self, from: &PathBuf, to: &PathBuf) -> Result<(), WebDavError> {
-        let src_path = self.resolve_path(from);
-        let dst_path = self.resolve_path(to);
-        
-        if let Some(parent) = dst_path.parent() {
-            fs::create_dir_all(parent).await?;
+    async fn move_resource(&self, from: &Path, to: &Path) -> Result<(), WebDavError> {
+        let src_path = self.resolve_path(from).await?;
+        let dst_path = self.resolve_path(to).await?;
+        if fs::metadata(&src_path).await.is_err() {
+            return Err(WebDavError::NotFound(from.to_path_buf()));
         }
+        self.check_destination(to, &dst_path).await?;
 
-        fs::rename(&src_path, &dst_path).await?;
+        let _guard = self.props_lock.lock().await;
+        let props = self.take_file_props(&src_path).await?;
+        self.forget_hashes(&src_path);
+        let result = match fs::rename(&src_path, &dst_path).await {
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+                self.move_across_devices(&src_path, &dst_path, from, to).await
+            }
+            other => other.map_err(Into::into),
+        };
+        if let Err(e) = result {
+            if props.is_some() {
+                self.put_file_props(&src_path, props).await?;
+            }
+            return Err(e);
+        }
+        if let Some(props) = props {
+            self.put_file_props(&dst_path, Some(props)).await?;
+        }
         Ok(())
     }
+
+    async fn get_properties(&self, path: &Path) -> Result<Vec<DeadProperty>, WebDavError> {
+        let full_path = self.resolve_path(path).await?;
+        let (file, key) = self
+            .props_location(&full_path)
+            .await
+            .map_err(|_| WebDavError::NotFound(path.to_path_buf()))?;
+        Ok(Self::load_props(&file).await?.remove(&key).unwrap_or_default())
+    }
+
+    async fn patch_properties(&self, path: &Path, updates: &[PropertyUpdate]) -> Result<(), WebDavError> {
+        let full_path = self.resolve_path(path).await?;
+        let _guard = self.props_lock.lock().await;
+        let (file, key) = self
+            .props_location(&full_path)
+            .await
+            .map_err(|_| WebDavError::NotFound(path.to_path_buf()))?;
+
+        // 所有修改先在内存中完成，最后一次性写回
+        let mut store = Self::load_props(&file).await?;
+        let mut props = store.remove(&key).unwrap_or_default();
+        for update in updates {
+            match update {
+                PropertyUpdate::Set(prop) => {
+                    props.retain(|p| p.namespace != prop.namespace || p.name != prop.name);
+                    props.push(prop.clone());
+                }
+                PropertyUpdate::Remove { namespace, name } => {
+                    props.retain(|p| p.namespace != *namespace || p.name != *name);
+                }
+            }
+        }
+        if !props.is_empty() {
+            store.insert(key, props);
+        }
+        Self::save_props(&file, &store).await
+    }
+}
+
+/// 由 inode、大小和纳秒级修改时间组成的 ETag
+fn metadata_etag(metadata: &std::fs::Metadata) -> String {
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+    #[cfg(not(unix))]
+    let inode = 0u64;
+    format!("\"{:x}-{:x}-{:x}\"", inode, metadata.len(), mtime_nanos(metadata))
+}
+
+/// 文件的（设备号, inode），非 Unix 平台返回 `None`
+fn file_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+fn mtime_nanos(metadata: &std::fs::Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos())
 }
 
-fn copy_dir_all<'a>(
-    src: &'a Path,
-    dst: &'a Path,
+/// 目录的 ETag 由成员的名称、大小和修改时间计算，成员增删或变化时随之改变。
+///
+/// 列出集合时每个子目录都会被读取一次（只读目录项和元数据，不读文件内容）。
+/// 这是有意的：同步客户端根据子集合的 ETag 判断是否需要进入该集合。
+async fn directory_etag(full_path: &Path) -> Result<String, WebDavError> {
+    let mut members = Vec::new();
+    let mut read_dir = fs::read_dir(full_path).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if is_internal(&entry.file_name()) {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        members.push((entry.file_name(), metadata.len(), mtime_nanos(&metadata)));
+    }
+    members.sort();
+
+    let mut hasher = Sha256::new();
+    for (name, len, mtime) in &members {
+        hasher.update(name.as_encoded_bytes());
+        hasher.update([0]);
+        hasher.update(len.to_le_bytes());
+        hasher.update(mtime.to_le_bytes());
+    }
+    let digest = format!("{:x}", hasher.finalize());
+    Ok(format!("\"{}\"", &digest[..32]))
+}
+
+/// 读取客户端通过 PROPPATCH 保存的 DAV: 属性的文本值，不能用作 HTTP 头的值时忽略
+fn stored_text(props: &[DeadProperty], name: &str) -> Option<String> {
+    let prop = props
+        .iter()
+        .find(|p| p.namespace == DAV_NAMESPACE && p.name == name)?;
+    let value = quick_xml::escape::unescape(prop.value.trim()).ok()?;
+    if value.is_empty() || http::HeaderValue::from_str(&value).is_err() {
+        return None;
+    }
+    Some(value.into_owned())
+}
+
+/// 根据文件头的魔数判断 MIME 类型
+async fn sniff_content_type(full_path: &Path) -> Option<String> {
+    let file = fs::File::open(full_path).await.ok()?;
+    let mut head = Vec::with_capacity(SNIFF_LEN as usize);
+    file.take(SNIFF_LEN).read_to_end(&mut head).await.ok()?;
+    infer::get(&head).map(|kind| kind.mime_type().to_string())
+}
+
+/// 后端自身使用的文件（属性文件和临时文件），不作为资源对外展示
+fn is_internal(name: &OsStr) -> bool {
+    name == PROPS_FILE || name.to_str().is_some_and(|name| name.starts_with(TEMP_PREFIX))
+}
+
+/// Windows 的设备名，带扩展名（例如 `nul.txt`）时同样指向设备
+const DEVICE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 请求路径中的文件名是否指向内部文件。
+///
+/// 大小写不敏感的文件系统（macOS、Windows）上 `.WEBDAV-PROPS.JSON` 是同一个文件，
+/// Windows 还会去掉文件名结尾的 `.` 和空格。
+fn aliases_internal(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    let name = name.trim_end_matches(['.', ' ']).to_ascii_lowercase();
+    name == PROPS_FILE || name.starts_with(TEMP_PREFIX)
+}
+
+/// 在部分平台上有特殊含义的文件名：Windows 设备名、结尾的 `.` 或空格，
+/// 以及 NTFS 备用数据流使用的 `:`（例如 `a.txt::$DATA`）
+fn is_reserved(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    if name.contains(':') || name.ends_with(['.', ' ']) {
+        return true;
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end_matches(' ');
+    DEVICE_NAMES.iter().any(|device| device.eq_ignore_ascii_case(stem))
+}
+
+/// 临时文件，在被重命名到目标路径之前如果被丢弃（出错或请求被取消）则自动删除
+struct TempFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempFile {
+    fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(format!("{}{}", TEMP_PREFIX, Uuid::new_v4().simple())),
+            persisted: false,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 文件已被重命名，不再需要清理
+    fn persist(mut self) {
+        self.persisted = true;
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 对目录调用 fsync，使其中的重命名操作落盘
+async fn sync_dir(dir: &Path) -> Result<(), WebDavError> {
+    #[cfg(unix)]
+    fs::File::open(dir).await?.sync_all().await?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// 递归删除目录，无法删除的成员记录到 `failures` 后继续处理其余成员。
+///
+/// 有成员失败时保留目录本身且不再单独报告（RFC 4918 第 9.6.1 节），目录无法读取或删除时返回错误。
+fn remove_dir_tree<'a>(
+    full_path: &'a Path,
+    path: &'a Path,
+    failures: &'a mut Vec<MemberStatus>,
 ) -> Pin<Box<dyn Future<Output = std::io::Result<()>> + Send + 'a>> {
     Box::pin(async move {
-        fs::create_dir_all(&dst).await?;
-        let mut read_dir = fs::read_dir(src).await?;
+        let before = failures.len();
+        let mut read_dir = fs::read_dir(full_path).await?;
         while let Some(entry) = read_dir.next_entry().await? {
-            let ty = entry.file_type().await?;
-            let src_path = entry.path();
-            let dst_path = dst.join(entry.file_name());
-            if ty.is_dir() {
-                copy_dir_all(&src_path, &dst_path).await?;
+            let name = entry.file_name();
+            let member = path.join(&name);
+            let is_dir = entry.file_type().await?.is_dir();
+            let result = if is_dir {
+                remove_dir_tree(&entry.path(), &member, failures).await
             } else {
-                fs::copy(&src_path, &dst_path).await?;
+                fs::remove_file(entry.path()).await
+            };
+            if let Err(e) = result {
+                // 内部文件不对外暴露，失败归到所在的集合
+                let (member, is_dir) = if is_internal(&name) {
+                    (path.to_path_buf(), true)
+                } else {
+                    (member, is_dir)
+                };
+                failures.push(MemberStatus::from_io(member, is_dir, &e));
             }
         }
+        if failures.len() == before {
+            fs::remove_dir(full_path).await?;
+        }
         Ok(())
     })
-} 
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(backend: &FileSystemBackend) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = backend.hash_cache.lock().unwrap().keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn hash_cache_forgets_removed_resources() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("docs")).unwrap();
+        for name in ["a.txt", "docs/b.txt", "docs/c.txt"] {
+            std::fs::write(root.join(name), name).unwrap();
+        }
+        let backend = FileSystemBackend::new(root).with_etag_mode(EtagMode::ContentHash);
+        backend.get_resource(Path::new("")).await.unwrap();
+        backend.get_resource(Path::new("docs")).await.unwrap();
+        assert_eq!(cached(&backend), [root.join("a.txt"), root.join("docs/b.txt"), root.join("docs/c.txt")]);
+
+        backend.delete(Path::new("docs/b.txt")).await.unwrap();
+        assert_eq!(cached(&backend), [root.join("a.txt"), root.join("docs/c.txt")]);
+
+        backend.move_resource(Path::new("docs"), Path::new("moved")).await.unwrap();
+        assert_eq!(cached(&backend), [root.join("a.txt")]);
+
+        let content: ByteStream = Box::pin(futures::stream::once(async { Ok(bytes::Bytes::from("new")) }));
+        backend.write_file(Path::new("a.txt"), content).await.unwrap();
+        assert!(cached(&backend).is_empty());
+    }
+
+    #[tokio::test]
+    async fn hash_cache_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = FileSystemBackend::new(dir.path()).with_etag_mode(EtagMode::ContentHash);
+        backend.hash_cache.lock().unwrap().extend(
+            (0..HASH_CACHE_CAPACITY).map(|i| (dir.path().join(format!("gone-{}", i)), (String::new(), String::new()))),
+        );
+        std::fs::write(dir.path().join("file.txt"), "data").unwrap();
+        backend.get_resource(Path::new("file.txt")).await.unwrap();
+        assert_eq!(backend.hash_cache.lock().unwrap().len(), HASH_CACHE_CAPACITY);
+        assert!(cached(&backend).contains(&dir.path().join("file.txt")));
+    }
+}
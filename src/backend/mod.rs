@@ -1,7 +1,11 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use std::path::PathBuf;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use crate::error::WebDavError;
 
 pub mod fs;
@@ -14,6 +18,67 @@ pub struct ResourceMetadata {
     pub modified: DateTime<Utc>,
     pub created: Option<DateTime<Utc>>,
     pub etag: String,
+    /// 文件的 MIME 类型，集合为 `None`
+    pub content_type: Option<String>,
+    /// 客户端通过 `getcontentlanguage` 属性设置的内容语言
+    pub content_language: Option<String>,
+    /// 底层文件的（设备号, inode），遍历时用来发现符号链接形成的环
+    pub file_id: Option<(u64, u64)>,
+    /// 随元数据一起读取的死属性，`None` 表示需要另外调用 [`Backend::get_properties`]
+    pub dead_properties: Option<Vec<DeadProperty>>,
+}
+
+/// 文件内容的字节流，读写文件时不需要把整个文件放入内存
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+/// 请求的作用深度（Depth 头）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Zero,
+    One,
+    Infinity,
+}
+
+/// 客户端存储的死属性（dead property），值为序列化后的 XML 片段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadProperty {
+    pub namespace: String,
+    pub name: String,
+    pub value: String,
+}
+
+/// PROPPATCH 中的一项修改
+#[derive(Debug, Clone)]
+pub enum PropertyUpdate {
+    Set(DeadProperty),
+    Remove { namespace: String, name: String },
+}
+
+/// 集合操作中处理失败的成员及其状态码，用于生成 207 Multi-Status 响应
+#[derive(Debug, Clone)]
+pub struct MemberStatus {
+    pub path: PathBuf,
+    /// 成员是否为集合，集合的 href 以 `/` 结尾
+    pub is_dir: bool,
+    pub status: http::StatusCode,
+}
+
+impl MemberStatus {
+    /// 根据 I/O 错误的类型选择成员的状态码
+    pub fn from_io(path: PathBuf, is_dir: bool, error: &std::io::Error) -> Self {
+        use http::StatusCode;
+        use std::io::ErrorKind;
+        let status = match error.kind() {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => StatusCode::FORBIDDEN,
+            ErrorKind::AlreadyExists | ErrorKind::DirectoryNotEmpty => StatusCode::CONFLICT,
+            ErrorKind::StorageFull | ErrorKind::QuotaExceeded | ErrorKind::FileTooLarge => {
+                StatusCode::INSUFFICIENT_STORAGE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        MemberStatus { path, is_dir, status }
+    }
 }
 
 #[derive(Debug)]
@@ -26,23 +91,44 @@ pub struct ResourceInfo {
 pub trait Backend: Send + Sync + 'static {
 
     /// 获取资源信息
-    async fn get_resource(&self, path: &PathBuf) -> Result<ResourceInfo, WebDavError>;
+    async fn get_resource(&self, path: &Path) -> Result<ResourceInfo, WebDavError>;
 
-    /// 读取文件内容
-    async fn read_file(&self, path: &PathBuf) -> Result<Bytes, WebDavError>;
+    /// 以流的形式读取文件内容，`range` 为 `None` 时读取整个文件
+    async fn read_file(&self, path: &Path, range: Option<Range<u64>>) -> Result<ByteStream, WebDavError>;
 
-    /// 写入文件内容
-    async fn write_file(&self, path: &PathBuf, content: Bytes) -> Result<(), WebDavError>;
+    /// 将字节流写入文件，已存在的文件会被替换。
+    ///
+    /// 返回 true 表示新建了文件。父集合不存在时返回 `Conflict`，目标是集合时返回 `MethodNotAllowed`。
+    async fn write_file(&self, path: &Path, content: ByteStream) -> Result<bool, WebDavError>;
 
     /// 创建目录
-    async fn create_dir(&self, path: &PathBuf) -> Result<(), WebDavError>;
+    async fn create_dir(&self, path: &Path) -> Result<(), WebDavError>;
+
+    /// 删除资源（文件或目录）。
+    ///
+    /// 删除集合时遇到无法删除的成员会继续处理其余成员，最后以 `Multistatus` 返回失败的成员。
+    async fn delete(&self, path: &Path) -> Result<(), WebDavError>;
 
-    /// 删除资源（文件或目录）
-    async fn delete(&self, path: &PathBuf) -> Result<(), WebDavError>;
+    /// 复制资源，`depth` 为 `Zero` 时只复制集合本身而不复制其成员。
+    ///
+    /// 目标必须不存在，父集合不存在时返回 `Conflict`。部分成员复制失败时以 `Multistatus`
+    /// 返回失败成员在目标位置的路径。
+    async fn copy(&self, from: &Path, to: &Path, depth: Depth) -> Result<(), WebDavError>;
 
-    /// 复制资源
-    async fn copy(&self, from: &PathBuf, to: &PathBuf) -> Result<(), WebDavError>;
+    /// 移动资源，目标必须不存在，父集合不存在时返回 `Conflict`
+    async fn move_resource(&self, from: &Path, to: &Path) -> Result<(), WebDavError>;
 
-    /// 移动资源
-    async fn move_resource(&self, from: &PathBuf, to: &PathBuf) -> Result<(), WebDavError>;
-} 
\ No newline at end of file
+    /// 读取资源的死属性，默认不支持死属性
+    async fn get_properties(&self, _path: &Path) -> Result<Vec<DeadProperty>, WebDavError> {
+        Ok(Vec::new())
+    }
+
+    /// 按顺序应用一组属性修改，必须原子完成：失败时不能留下部分修改
+    async fn patch_properties(
+        &self,
+        path: &Path,
+        _updates: &[PropertyUpdate],
+    ) -> Result<(), WebDavError> {
+        Err(WebDavError::PermissionDenied(path.to_path_buf()))
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use thiserror::Error;
+use tokio_rustls::rustls::{self, pki_types::PrivateKeyDer};
+
+use crate::auth::{is_supported_hash, DigestCredentials, JwtVerifier, TokenDatabase, UserDatabase};
+use crate::backend::fs::{Durability, EtagMode, FileSystemBackend, SymlinkPolicy};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Cannot read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid configuration file: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// 服务器配置，对应 TOML 配置文件，未出现的字段使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    /// 除 `server` 中的主挂载点之外的其他挂载点
+    pub mounts: Vec<MountConfig>,
+    pub users: Vec<UserConfig>,
+    pub auth: AuthConfig,
+    pub storage: StorageConfig,
+    pub limits: LimitsConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub port: u16,
+    /// 主挂载点的存储目录，配置了 `[[mounts]]` 时可以省略
+    pub root: Option<PathBuf>,
+    pub prefix: String,
+    pub read_only: bool,
+    /// 每个用户只能访问主挂载点下以用户名命名的目录
+    pub home_directories: bool,
+    /// 家目录模式下管理员可以访问所有用户的目录
+    pub admin_sees_all: bool,
+    /// 日志过滤规则，语法同 `RUST_LOG`
+    pub log: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1".to_string(),
+            port: 3000,
+            root: None,
+            prefix: "/dav".to_string(),
+            read_only: false,
+            home_directories: false,
+            admin_sees_all: false,
+            log: "info".to_string(),
+        }
+    }
+}
+
+/// 将一个存储目录挂载到一个 URL 前缀下
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MountConfig {
+    pub prefix: String,
+    pub root: PathBuf,
+    #[serde(default)]
+    pub read_only: bool,
+    /// 每个用户只能访问 `root/<用户名>`，第一次访问时创建
+    #[serde(default)]
+    pub home_directories: bool,
+    /// 家目录模式下管理员可以访问所有用户的目录
+    #[serde(default)]
+    pub admin_sees_all: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    pub name: String,
+    /// bcrypt 或 argon2 格式的密码哈希
+    pub password_hash: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// 配置了任何用户时所有请求都需要认证
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// `WWW-Authenticate` 质询中的 realm
+    pub realm: String,
+    /// htpasswd 格式的用户文件，与 `[[users]]` 合并，同名时以 `[[users]]` 为准
+    pub users_file: Option<PathBuf>,
+    /// htdigest 格式的 Digest 认证文件，设置后启用 Digest 认证
+    pub digest_file: Option<PathBuf>,
+    /// Digest 认证 nonce 的有效期（秒）
+    pub nonce_lifetime: u64,
+    /// Bearer 认证的不透明令牌文件
+    pub tokens_file: Option<PathBuf>,
+    /// Bearer 认证的 JWT 校验密钥
+    pub jwt: Option<JwtConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JwtConfig {
+    /// `HS256`、`RS256` 或 `EdDSA`
+    pub algorithm: String,
+    /// HS256 的共享密钥
+    pub secret: Option<String>,
+    /// RS256 或 EdDSA 的 PEM 格式公钥
+    pub public_key: Option<PathBuf>,
+    /// 要求 `iss` 声明等于此值
+    pub issuer: Option<String>,
+    /// 要求 `aud` 声明包含此值
+    pub audience: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            realm: "webdav-rs".to_string(),
+            users_file: None,
+            digest_file: None,
+            nonce_lifetime: 300,
+            tokens_file: None,
+            jwt: None,
+        }
+    }
+}
+
+/// 所有挂载点共用的文件系统后端设置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// `metadata` 或 `content-hash`
+    pub etag_mode: EtagMode,
+    /// `relaxed`、`file` 或 `full`
+    pub durability: Durability,
+    /// `deny`、`follow-within-root` 或 `follow-all`
+    pub symlinks: SymlinkPolicy,
+    /// 扩展名未知时根据文件头判断 MIME 类型
+    pub content_sniffing: bool,
+    /// 扩展名到 MIME 类型的映射，优先于内置的映射表
+    pub mime_types: HashMap<String, String>,
+}
+
+impl StorageConfig {
+    /// 按照设置创建 `root` 上的后端
+    pub fn backend(&self, root: &Path) -> FileSystemBackend {
+        FileSystemBackend::new(root)
+            .with_etag_mode(self.etag_mode)
+            .with_durability(self.durability)
+            .with_symlink_policy(self.symlinks)
+            .with_content_sniffing(self.content_sniffing)
+            .with_mime_overrides(self.mime_types.clone())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// PUT 请求体的最大字节数，不设置时不限制
+    pub max_upload_size: Option<u64>,
+    /// 是否允许 `Depth: infinity` 的 PROPFIND
+    pub allow_infinite_depth: bool,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_size: None,
+            allow_infinite_depth: true,
+        }
+    }
+}
+
+/// PEM 格式的证书链和私钥
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// 监听地址
+    pub fn addr(&self) -> Result<SocketAddr, ConfigError> {
+        let ip: IpAddr = self.server.bind.parse().map_err(|_| {
+            ConfigError::Invalid(format!("invalid bind address {:?}", self.server.bind))
+        })?;
+        Ok(SocketAddr::new(ip, self.server.port))
+    }
+
+    /// 所有生效的挂载点。
+    ///
+    /// `server.root` 设置时（或者没有配置其他挂载点时使用 `./storage`）作为第一个挂载点。
+    pub fn mounts(&self) -> Vec<MountConfig> {
+        let mut mounts = Vec::with_capacity(self.mounts.len() + 1);
+        if self.server.root.is_some() || self.mounts.is_empty() {
+            mounts.push(MountConfig {
+                prefix: self.server.prefix.clone(),
+                root: self.server.root.clone().unwrap_or_else(|| PathBuf::from("./storage")),
+                read_only: self.server.read_only,
+                home_directories: self.server.home_directories,
+                admin_sees_all: self.server.admin_sees_all,
+            });
+        }
+        mounts.extend(self.mounts.iter().cloned());
+        mounts
+    }
+
+    /// 合并用户文件和 `[[users]]` 中的用户
+    pub fn user_database(&self) -> Result<UserDatabase, ConfigError> {
+        let mut users = UserDatabase::new();
+        if let Some(path) = &self.auth.users_file {
+            users.load_htpasswd(path).map_err(|source| ConfigError::Io {
+                path: path.clone(),
+                source,
+            })?;
+        }
+        for user in &self.users {
+            users.insert(&user.name, &user.password_hash, user.admin);
+        }
+        Ok(users)
+    }
+
+    /// 是否配置了任何认证方式
+    pub fn auth_enabled(&self) -> bool {
+        !self.users.is_empty()
+            || self.auth.users_file.is_some()
+            || self.auth.digest_file.is_some()
+            || self.auth.tokens_file.is_some()
+            || self.auth.jwt.is_some()
+    }
+
+    /// 读取 Digest 认证文件中属于 `auth.realm` 的用户，未配置时返回 `None`
+    pub fn digest_credentials(&self) -> Result<Option<DigestCredentials>, ConfigError> {
+        let Some(path) = &self.auth.digest_file else {
+            return Ok(None);
+        };
+        let mut credentials = DigestCredentials::new();
+        credentials
+            .load_htdigest(path, &self.auth.realm)
+            .map_err(|source| ConfigError::Io { path: path.clone(), source })?;
+        if credentials.is_empty() {
+            return Err(ConfigError::Invalid(format!(
+                "{} has no users for realm {:?}",
+                path.display(),
+                self.auth.realm
+            )));
+        }
+        Ok(Some(credentials))
+    }
+
+    /// 读取 Bearer 认证的令牌文件，未配置时返回 `None`
+    pub fn token_database(&self) -> Result<Option<TokenDatabase>, ConfigError> {
+        let Some(path) = &self.auth.tokens_file else {
+            return Ok(None);
+        };
+        let mut tokens = TokenDatabase::new();
+        tokens
+            .load(path)
+            .map_err(|e| ConfigError::Invalid(format!("{}: {}", path.display(), e)))?;
+        Ok(Some(tokens))
+    }
+
+    /// 根据 `[auth.jwt]` 创建 JWT 校验器，未配置时返回 `None`
+    pub fn jwt_verifier(&self) -> Result<Option<JwtVerifier>, ConfigError> {
+        let Some(jwt) = &self.auth.jwt else {
+            return Ok(None);
+        };
+        let key = match (jwt.algorithm.as_str(), &jwt.secret, &jwt.public_key) {
+            ("HS256", Some(secret), None) => secret.as_bytes().to_vec(),
+            ("HS256", _, _) => {
+                return Err(ConfigError::Invalid("auth.jwt: HS256 needs secret and no public_key".to_string()))
+            }
+            (_, None, Some(path)) => std::fs::read(path).map_err(|source| ConfigError::Io {
+                path: path.clone(),
+                source,
+            })?,
+            (algorithm, _, _) => {
+                return Err(ConfigError::Invalid(format!(
+                    "auth.jwt: {} needs public_key and no secret",
+                    algorithm
+                )))
+            }
+        };
+        JwtVerifier::new(&jwt.algorithm, &key, jwt.issuer.as_deref(), jwt.audience.as_deref())
+            .map(Some)
+            .map_err(|e| ConfigError::Invalid(format!("auth.jwt: {}", e)))
+    }
+
+    /// 检查配置的一致性，以及引用的文件是否可用
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.addr()?;
+
+        let mut prefixes = HashSet::new();
+        for mount in self.mounts() {
+            let prefix = normalize_prefix(&mount.prefix);
+            if !prefixes.insert(prefix.clone()) {
+                return Err(ConfigError::Invalid(format!("duplicate mount prefix {:?}", mount.prefix)));
+            }
+            if mount.root.exists() && !mount.root.is_dir() {
+                return Err(ConfigError::Invalid(format!(
+                    "mount root {} is not a directory",
+                    mount.root.display()
+                )));
+            }
+            if mount.home_directories && !self.auth_enabled() {
+                return Err(ConfigError::Invalid(format!(
+                    "mount {:?} uses home directories but no authentication is configured",
+                    mount.prefix
+                )));
+            }
+        }
+
+        for (extension, mime) in &self.storage.mime_types {
+            if mime.parse::<mime_guess::mime::Mime>().is_err() || http::HeaderValue::from_str(mime).is_err() {
+                return Err(ConfigError::Invalid(format!(
+                    "invalid MIME type {:?} for extension {:?}",
+                    mime, extension
+                )));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for user in &self.users {
+            if user.name.is_empty() || user.name.contains(':') {
+                return Err(ConfigError::Invalid(format!("invalid user name {:?}", user.name)));
+            }
+            if !names.insert(user.name.as_str()) {
+                return Err(ConfigError::Invalid(format!("duplicate user {:?}", user.name)));
+            }
+            if !is_supported_hash(&user.password_hash) {
+                return Err(ConfigError::Invalid(format!(
+                    "user {:?} needs a bcrypt or argon2 password hash",
+                    user.name
+                )));
+            }
+        }
+        let users = self.user_database()?;
+        let digest = self.digest_credentials()?;
+        let tokens = self.token_database()?;
+        let jwt = self.jwt_verifier()?;
+        // 配置了认证却没有可用的凭据时拒绝启动，而不是退化为匿名访问
+        let has_credentials = !users.is_empty()
+            || digest.is_some()
+            || tokens.is_some_and(|tokens| !tokens.is_empty())
+            || jwt.is_some();
+        if self.auth_enabled() && !has_credentials {
+            return Err(ConfigError::Invalid(
+                "authentication is configured but no users or tokens were loaded".to_string(),
+            ));
+        }
+        if self.auth.nonce_lifetime == 0 {
+            return Err(ConfigError::Invalid("auth.nonce_lifetime must be positive".to_string()));
+        }
+
+        if let Some(tls) = &self.tls {
+            tls.server_config()?;
+        }
+        Ok(())
+    }
+}
+
+impl TlsConfig {
+    /// 读取证书和私钥，生成 rustls 的服务端配置
+    pub fn server_config(&self) -> Result<Arc<rustls::ServerConfig>, ConfigError> {
+        let certs = rustls_pemfile::certs(&mut open(&self.cert)?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|source| ConfigError::Io { path: self.cert.clone(), source })?;
+        if certs.is_empty() {
+            return Err(ConfigError::Invalid(format!("no certificate found in {}", self.cert.display())));
+        }
+        let key: PrivateKeyDer = rustls_pemfile::private_key(&mut open(&self.key)?)
+            .map_err(|source| ConfigError::Io { path: self.key.clone(), source })?
+            .ok_or_else(|| ConfigError::Invalid(format!("no private key found in {}", self.key.display())))?;
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(|e| ConfigError::Invalid(format!("invalid TLS certificate or key: {}", e)))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, ConfigError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })
+}
+
+/// 与 `WebDavHandler::with_mount_prefix` 相同的规范化，用于比较挂载点
+fn normalize_prefix(prefix: &str) -> String {
+    prefix.trim_matches('/').to_string()
+}
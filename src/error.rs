@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::backend::MemberStatus;
+
 #[derive(Error, Debug)]
 pub enum WebDavError {
     #[error("Resource not found: {0}")]
@@ -18,9 +20,51 @@ pub enum WebDavError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    /// 条件 GET/HEAD 命中缓存，携带资源当前的 ETag
+    #[error("Not modified")]
+    NotModified(String),
+
+    /// 违反 RFC 4918 定义的前置或后置条件，响应体为带条件元素的 `D:error`
+    #[error("Condition failed: {condition}")]
+    Condition {
+        status: http::StatusCode,
+        condition: &'static str,
+        /// 条件元素中列出的资源，例如 `lock-token-submitted` 中被锁定的资源
+        hrefs: Vec<String>,
+    },
+
+    /// Range 中没有可以满足的区间，携带资源的当前长度
+    #[error("Range not satisfiable")]
+    RangeNotSatisfiable(u64),
+
+    /// 缺少或无效的凭据，携带每种认证方案的 `WWW-Authenticate` 质询
+    #[error("Authentication required")]
+    Unauthorized(Vec<String>),
+
     #[error("Lock conflict")]
     LockConflict,
 
+    /// 集合操作中部分成员失败，响应为列出这些成员的 207 Multi-Status
+    #[error("{} members failed", .0.len())]
+    Multistatus(Vec<MemberStatus>),
+
+    /// 请求体超过配置的上限，携带上限字节数
+    #[error("Payload too large: limit is {0} bytes")]
+    PayloadTooLarge(u64),
+
+    /// 目标位于其他服务器上
+    #[error("Bad gateway: {0}")]
+    BadGateway(String),
+
     #[error("XML error: {0}")]
     XmlError(String),
 
@@ -35,9 +79,20 @@ impl WebDavError {
             WebDavError::NotFound(_) => StatusCode::NOT_FOUND,
             WebDavError::AlreadyExists(_) => StatusCode::CONFLICT,
             WebDavError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            WebDavError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            WebDavError::Conflict(_) => StatusCode::CONFLICT,
+            WebDavError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            WebDavError::NotModified(_) => StatusCode::NOT_MODIFIED,
+            WebDavError::Condition { status, .. } => *status,
+            WebDavError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            WebDavError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             WebDavError::LockConflict => StatusCode::LOCKED,
+            WebDavError::Multistatus(_) => StatusCode::MULTI_STATUS,
             WebDavError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            WebDavError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            WebDavError::BadGateway(_) => StatusCode::BAD_GATEWAY,
+            WebDavError::XmlError(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,108 @@
+use axum::http::{header, HeaderMap, Method};
+use chrono::{DateTime, Utc};
+
+use crate::{backend::ResourceMetadata, error::WebDavError};
+
+/// 按 RFC 7232 第 6 节的顺序评估 HTTP 前置条件。
+///
+/// `resource` 为 `None` 表示目标资源不存在。条件不满足时返回
+/// `PreconditionFailed`（412），GET/HEAD 的缓存命中返回 `NotModified`（304）。
+pub(crate) fn check_preconditions(
+    headers: &HeaderMap,
+    method: &Method,
+    resource: Option<&ResourceMetadata>,
+) -> Result<(), WebDavError> {
+    let is_read = *method == Method::GET || *method == Method::HEAD;
+
+    if let Some(value) = header_str(headers, header::IF_MATCH) {
+        let matched = match resource {
+            None => false,
+            Some(_) if value.trim() == "*" => true,
+            Some(resource) => etag_list_matches(value, &resource.etag, true),
+        };
+        if !matched {
+            return Err(WebDavError::PreconditionFailed("If-Match".to_string()));
+        }
+    } else if let (Some(date), Some(resource)) = (
+        header_date(headers, header::IF_UNMODIFIED_SINCE),
+        resource,
+    ) {
+        if resource.modified.timestamp() > date.timestamp() {
+            return Err(WebDavError::PreconditionFailed("If-Unmodified-Since".to_string()));
+        }
+    }
+
+    if let Some(value) = header_str(headers, header::IF_NONE_MATCH) {
+        let matched = match resource {
+            None => false,
+            Some(_) if value.trim() == "*" => true,
+            Some(resource) => etag_list_matches(value, &resource.etag, false),
+        };
+        if matched {
+            return Err(match resource {
+                Some(resource) if is_read => WebDavError::NotModified(resource.etag.clone()),
+                _ => WebDavError::PreconditionFailed("If-None-Match".to_string()),
+            });
+        }
+    } else if let (true, Some(date), Some(resource)) = (
+        is_read,
+        header_date(headers, header::IF_MODIFIED_SINCE),
+        resource,
+    ) {
+        if resource.modified.timestamp() <= date.timestamp() {
+            return Err(WebDavError::NotModified(resource.etag.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// 格式化为 RFC 1123 的 HTTP 日期，例如 `Sun, 06 Nov 1994 08:49:37 GMT`
+pub(crate) fn http_date(date: &DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// 无法解析的日期按 RFC 7232 的要求忽略
+fn header_date(headers: &HeaderMap, name: header::HeaderName) -> Option<DateTime<Utc>> {
+    let value = header_str(headers, name)?;
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// 判断逗号分隔的实体标签列表中是否有与 `etag` 匹配的项，`strong` 决定使用强比较还是弱比较
+pub(crate) fn etag_list_matches(list: &str, etag: &str, strong: bool) -> bool {
+    let (etag_weak, etag_value) = split_etag(etag);
+    parse_etag_list(list).into_iter().any(|(weak, value)| {
+        value == etag_value && (!strong || (!weak && !etag_weak))
+    })
+}
+
+fn split_etag(etag: &str) -> (bool, &str) {
+    match etag.strip_prefix("W/") {
+        Some(rest) => (true, rest),
+        None => (false, etag),
+    }
+}
+
+/// 解析 `"a", W/"b"` 形式的列表，引号内的逗号不作为分隔符
+fn parse_etag_list(list: &str) -> Vec<(bool, &str)> {
+    let mut tags = Vec::new();
+    let mut rest = list.trim_start();
+    while !rest.is_empty() {
+        let (weak, tagged) = split_etag(rest);
+        if !tagged.starts_with('"') {
+            break;
+        }
+        let Some(end) = tagged[1..].find('"') else {
+            break;
+        };
+        tags.push((weak, &tagged[..end + 2]));
+        rest = tagged[end + 2..].trim_start().trim_start_matches(',').trim_start();
+    }
+    tags
+}
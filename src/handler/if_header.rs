@@ -0,0 +1,221 @@
+use axum::http::HeaderMap;
+
+use crate::error::WebDavError;
+
+const IF: &str = "if";
+
+/// RFC 4918 第 10.4 节定义的 `If` 请求头
+#[derive(Debug, Default)]
+pub(crate) struct IfHeader {
+    pub lists: Vec<IfList>,
+}
+
+/// 一个括号列表，`resource` 为 Tagged-list 的资源 URL，No-tag-list 时为 `None`
+#[derive(Debug)]
+pub(crate) struct IfList {
+    pub resource: Option<String>,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Condition {
+    pub not: bool,
+    pub kind: ConditionKind,
+}
+
+#[derive(Debug)]
+pub(crate) enum ConditionKind {
+    /// 状态令牌（Coded-URL），例如锁令牌
+    StateToken(String),
+    /// 方括号中的实体标签
+    ETag(String),
+}
+
+impl IfHeader {
+    /// 读取并解析 `If` 头，请求未携带时返回 `None`
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, WebDavError> {
+        match headers.get(IF) {
+            None => Ok(None),
+            Some(value) => {
+                let value = value
+                    .to_str()
+                    .map_err(|_| WebDavError::InvalidInput("Invalid If header".to_string()))?;
+                Self::parse(value).map(Some)
+            }
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, WebDavError> {
+        let invalid = || WebDavError::InvalidInput(format!("Invalid If header: {}", value));
+        let mut header = IfHeader::default();
+        let mut resource: Option<String> = None;
+        let mut tagged = None;
+        let mut rest = value.trim_start();
+
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('<') {
+                // Resource-Tag 不能出现在 No-tag-list 之后
+                if tagged == Some(false) {
+                    return Err(invalid());
+                }
+                let end = tail.find('>').ok_or_else(invalid)?;
+                resource = Some(tail[..end].to_string());
+                tagged = Some(true);
+                rest = tail[end + 1..].trim_start();
+            } else if let Some(tail) = rest.strip_prefix('(') {
+                if tagged.is_none() {
+                    tagged = Some(false);
+                }
+                let (conditions, tail) = parse_list(tail).ok_or_else(invalid)?;
+                header.lists.push(IfList {
+                    resource: resource.clone(),
+                    conditions,
+                });
+                rest = tail.trim_start();
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        if header.lists.is_empty() {
+            return Err(invalid());
+        }
+        Ok(header)
+    }
+
+    /// 头中出现的全部状态令牌，视为客户端提交的锁令牌
+    pub fn tokens(&self) -> Vec<String> {
+        self.lists
+            .iter()
+            .flat_map(|list| &list.conditions)
+            .filter_map(|condition| match &condition.kind {
+                ConditionKind::StateToken(token) => Some(token.clone()),
+                ConditionKind::ETag(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// 解析 `(` 之后直到 `)` 的条件列表，返回条件和剩余的输入
+fn parse_list(mut rest: &str) -> Option<(Vec<Condition>, &str)> {
+    let mut conditions = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(tail) = rest.strip_prefix(')') {
+            return (!conditions.is_empty()).then_some((conditions, tail));
+        }
+
+        let not = rest.get(..3).is_some_and(|s| s.eq_ignore_ascii_case("not"));
+        if not {
+            rest = rest[3..].trim_start();
+        }
+
+        let kind = if let Some(tail) = rest.strip_prefix('<') {
+            let end = tail.find('>')?;
+            rest = &tail[end + 1..];
+            ConditionKind::StateToken(tail[..end].to_string())
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']')?;
+            rest = &tail[end + 1..];
+            ConditionKind::ETag(tail[..end].trim().to_string())
+        } else {
+            return None;
+        };
+        conditions.push(Condition { not, kind });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(condition: &Condition) -> Option<&str> {
+        match &condition.kind {
+            ConditionKind::StateToken(token) => Some(token),
+            ConditionKind::ETag(_) => None,
+        }
+    }
+
+    fn etag(condition: &Condition) -> Option<&str> {
+        match &condition.kind {
+            ConditionKind::ETag(tag) => Some(tag),
+            ConditionKind::StateToken(_) => None,
+        }
+    }
+
+    #[test]
+    fn no_tag_list() {
+        let header = IfHeader::parse("(<opaquelocktoken:a>) (<opaquelocktoken:b>)").unwrap();
+        assert_eq!(header.lists.len(), 2);
+        assert!(header.lists.iter().all(|list| list.resource.is_none()));
+        assert_eq!(token(&header.lists[0].conditions[0]), Some("opaquelocktoken:a"));
+        assert_eq!(header.tokens(), ["opaquelocktoken:a", "opaquelocktoken:b"]);
+    }
+
+    #[test]
+    fn tagged_lists() {
+        let header = IfHeader::parse(
+            "<http://example.com/dav/a> (<opaquelocktoken:a>) (Not <DAV:no-lock>) \
+             </dav/b> ([\"etag-b\"])",
+        )
+        .unwrap();
+        let resources: Vec<_> = header.lists.iter().map(|list| list.resource.as_deref()).collect();
+        assert_eq!(
+            resources,
+            [Some("http://example.com/dav/a"), Some("http://example.com/dav/a"), Some("/dav/b")]
+        );
+        assert_eq!(etag(&header.lists[2].conditions[0]), Some("\"etag-b\""));
+    }
+
+    #[test]
+    fn not_and_multiple_conditions() {
+        let header = IfHeader::parse("(not <opaquelocktoken:a> [W/\"x\"] NOT [\"y\"])").unwrap();
+        let conditions = &header.lists[0].conditions;
+        assert_eq!(conditions.len(), 3);
+        assert!(conditions[0].not);
+        assert_eq!(token(&conditions[0]), Some("opaquelocktoken:a"));
+        assert!(!conditions[1].not);
+        assert_eq!(etag(&conditions[1]), Some("W/\"x\""));
+        assert!(conditions[2].not);
+        assert_eq!(etag(&conditions[2]), Some("\"y\""));
+        assert_eq!(header.tokens(), ["opaquelocktoken:a"]);
+    }
+
+    #[test]
+    fn etag_brackets_are_trimmed() {
+        let header = IfHeader::parse("([ \"abc\" ])").unwrap();
+        assert_eq!(etag(&header.lists[0].conditions[0]), Some("\"abc\""));
+        assert!(header.tokens().is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        for value in [
+            "",
+            "   ",
+            "()",
+            "(",
+            "(<opaquelocktoken:a>",
+            "(<opaquelocktoken:a)",
+            "([\"abc\")",
+            "(opaquelocktoken:a)",
+            "(Not)",
+            "<http://example.com/a>",
+            "<http://example.com/a",
+            "(<opaquelocktoken:a>) <http://example.com/a> (<opaquelocktoken:b>)",
+            "(<opaquelocktoken:a>) junk",
+        ] {
+            assert!(IfHeader::parse(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+
+    #[test]
+    fn from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(IfHeader::from_headers(&headers).unwrap().is_none());
+        headers.insert(IF, "(<opaquelocktoken:a>)".parse().unwrap());
+        assert_eq!(IfHeader::from_headers(&headers).unwrap().unwrap().lists.len(), 1);
+        headers.insert(IF, "nonsense".parse().unwrap());
+        assert!(IfHeader::from_headers(&headers).is_err());
+    }
+}
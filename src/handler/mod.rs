@@ -1,47 +1,376 @@
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 use axum::{
     body::{Body, Bytes},
     extract::Path,
     response::{IntoResponse, Response},
-    http::{Request, StatusCode, header},
+    http::{HeaderMap, Method, Request, StatusCode, header},
 };
-use futures::StreamExt;
-use tokio_util::io::StreamReader;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use uuid::Uuid;
 
 use crate::{
-    backend::Backend,
+    auth::Principal,
+    backend::{Backend, ByteStream, Depth, PropertyUpdate, ResourceMetadata},
     error::WebDavError,
-    xml,
+    lock::{LockManager, DEFAULT_LOCK_TIMEOUT, MAX_LOCK_TIMEOUT},
+    xml::{self, PropName},
 };
 
+mod conditional;
+mod if_header;
+mod props;
+mod range;
+pub(crate) mod uri;
+
+use if_header::{ConditionKind, IfHeader};
+
 const DESTINATION: &str = "destination";
+const DEPTH: &str = "depth";
+const TIMEOUT: &str = "timeout";
+const LOCK_TOKEN: &str = "lock-token";
+const OVERWRITE: &str = "overwrite";
+
+/// 默认的挂载点
+const DEFAULT_MOUNT_PREFIX: &str = "/dav";
+/// XML 请求体的大小上限
+const MAX_XML_BODY: usize = 1 << 20;
 
 #[derive(Clone)]
 pub struct WebDavHandler<B: Backend> {
     backend: Arc<B>,
+    locks: Arc<LockManager>,
+    allow_infinite_depth: bool,
+    /// 服务挂载的 URL 前缀，不以 `/` 结尾，挂载在根路径时为空
+    mount_prefix: String,
+    read_only: bool,
+    max_upload_size: Option<u64>,
+    /// 每个用户只能访问挂载点下以用户名命名的目录
+    home_directories: bool,
+    /// 家目录模式下管理员可以访问所有用户的目录
+    admin_sees_all: bool,
+    /// 已经创建过的家目录
+    homes: Arc<Mutex<HashSet<String>>>,
+    /// 同一路径上的 PUT 依次执行，前置条件在写入完成前保持成立
+    put_locks: Arc<PathLocks>,
 }
 
 impl<B: Backend> WebDavHandler<B> {
     pub fn new(backend: B) -> Self {
         Self {
             backend: Arc::new(backend),
+            locks: Arc::new(LockManager::new()),
+            allow_infinite_depth: true,
+            mount_prefix: DEFAULT_MOUNT_PREFIX.to_string(),
+            read_only: false,
+            max_upload_size: None,
+            home_directories: false,
+            admin_sees_all: false,
+            homes: Arc::new(Mutex::new(HashSet::new())),
+            put_locks: Arc::new(PathLocks::default()),
+        }
+    }
+
+    /// 启用家目录：用户只能访问 `<挂载点>/<用户名>`，第一次访问时创建。
+    ///
+    /// 根集合只读，列表中只包含自己的家目录；`admin_sees_all` 时管理员不受限制。
+    pub fn with_home_directories(mut self, enabled: bool, admin_sees_all: bool) -> Self {
+        self.home_directories = enabled;
+        self.admin_sees_all = admin_sees_all;
+        self
+    }
+
+    /// 只读模式下所有修改资源或锁的请求返回 403
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// 设置 PUT 请求体的最大字节数，超过时返回 413
+    pub fn with_max_upload_size(mut self, limit: Option<u64>) -> Self {
+        self.max_upload_size = limit;
+        self
+    }
+
+    /// 设置挂载点，例如 `/files/`，`/` 表示挂载在根路径
+    pub fn with_mount_prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        self.mount_prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", prefix)
+        };
+        self
+    }
+
+    /// 从请求的原始 URL 路径中去掉挂载点，不在挂载点下时返回 `None`。
+    ///
+    /// 返回的路径仍是百分号编码的，由各个处理方法解码。
+    pub fn strip_mount_prefix<'a>(&self, path: &'a str) -> Option<&'a str> {
+        uri::strip_mount_prefix(path, &self.mount_prefix)
+    }
+
+    /// 设置是否允许 `Depth: infinity` 的 PROPFIND，不允许时返回 403
+    pub fn with_infinite_depth(mut self, allow: bool) -> Self {
+        self.allow_infinite_depth = allow;
+        self
+    }
+
+    /// 在调用后端之前检查是否允许修改 `path`
+    async fn check_writable(&self, principal: Option<&Principal>, path: &FsPath) -> Result<(), WebDavError> {
+        if self.read_only {
+            return Err(WebDavError::PermissionDenied(path.to_path_buf()));
+        }
+        self.check_access(principal, path, true).await
+    }
+
+    /// 在调用后端之前检查是否允许读取 `path`
+    async fn check_readable(&self, principal: Option<&Principal>, path: &FsPath) -> Result<(), WebDavError> {
+        self.check_access(principal, path, false).await
+    }
+
+    async fn check_access(&self, principal: Option<&Principal>, path: &FsPath, write: bool) -> Result<(), WebDavError> {
+        if !self.allows(principal, path, write) {
+            return Err(WebDavError::PermissionDenied(path.to_path_buf()));
+        }
+        if let Some(principal) = principal {
+            self.ensure_home(principal).await?;
+        }
+        Ok(())
+    }
+
+    /// 检查认证用户的访问范围（令牌的路径前缀包含挂载点）和家目录限制
+    fn allows(&self, principal: Option<&Principal>, path: &FsPath, write: bool) -> bool {
+        if let Some(principal) = principal {
+            let url_path = FsPath::new("/").join(self.mount_prefix.trim_start_matches('/')).join(path);
+            if !principal.access.allows(&url_path, write) {
+                return false;
+            }
+        }
+        if !self.home_directories {
+            return true;
+        }
+        match principal {
+            None => false,
+            Some(principal) if principal.admin && self.admin_sees_all => true,
+            Some(principal) => match path.components().next() {
+                None => !write,
+                Some(first) => is_home_name(&principal.name) && first.as_os_str() == principal.name.as_str(),
+            },
+        }
+    }
+
+    /// 用户的家目录，用户可以访问所有资源时返回 `None`
+    fn confined_home<'p>(&self, principal: Option<&'p Principal>) -> Option<&'p str> {
+        let principal = principal?;
+        if !self.home_directories || (principal.admin && self.admin_sees_all) {
+            return None;
+        }
+        Some(&principal.name)
+    }
+
+    /// 受限的用户不能删除、移动或覆盖自己的家目录
+    fn check_removable(&self, principal: Option<&Principal>, path: &FsPath) -> Result<(), WebDavError> {
+        if self.confined_home(principal).is_some_and(|home| path == FsPath::new(home)) {
+            return Err(WebDavError::PermissionDenied(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// 根集合的 ETag 和修改时间随所有用户的活动变化，受限的用户看到的是自己家目录的值
+    async fn visible_metadata(&self, principal: Option<&Principal>, mut metadata: ResourceMetadata) -> ResourceMetadata {
+        let Some(home) = self.confined_home(principal) else {
+            return metadata;
+        };
+        if metadata.path.components().next().is_some() {
+            return metadata;
+        }
+        match self.backend.get_resource(FsPath::new(home)).await {
+            Ok(info) => {
+                metadata.etag = info.metadata.etag;
+                metadata.modified = info.metadata.modified;
+                metadata.created = info.metadata.created;
+            }
+            Err(_) => {
+                metadata.etag = "\"root\"".to_string();
+                metadata.modified = DateTime::<Utc>::default();
+                metadata.created = None;
+            }
         }
+        metadata
+    }
+
+    /// 家目录模式下用户第一次访问时创建家目录
+    async fn ensure_home(&self, principal: &Principal) -> Result<(), WebDavError> {
+        if !self.home_directories || self.read_only || !is_home_name(&principal.name) {
+            return Ok(());
+        }
+        if self.homes.lock().unwrap().contains(&principal.name) {
+            return Ok(());
+        }
+        self.backend.create_dir(FsPath::new(&principal.name)).await?;
+        self.homes.lock().unwrap().insert(principal.name.clone());
+        Ok(())
+    }
+
+    /// 检查写操作是否被锁阻止，`membership` 为 true 时同时检查父集合（增删成员）。
+    ///
+    /// 未提交锁令牌时返回 423，响应体的 `lock-token-submitted` 列出被锁定的资源。
+    fn check_locks(
+        &self,
+        principal: Option<&Principal>,
+        path: &FsPath,
+        headers: &HeaderMap,
+        deep: bool,
+        membership: bool,
+    ) -> Result<(), WebDavError> {
+        let tokens = IfHeader::from_headers(headers)
+            .ok()
+            .flatten()
+            .map(|if_header| if_header.tokens())
+            .unwrap_or_default();
+
+        let principal = principal.map(|principal| principal.name.as_str());
+        let mut blocked = self.locks.blocking_roots(path, &tokens, principal, deep);
+        if membership {
+            if let Some(parent) = path.parent() {
+                blocked.extend(self.locks.blocking_roots(parent, &tokens, principal, false));
+            }
+        }
+        if blocked.is_empty() {
+            return Ok(());
+        }
+
+        blocked.sort();
+        blocked.dedup();
+        Err(WebDavError::Condition {
+            status: StatusCode::LOCKED,
+            condition: "lock-token-submitted",
+            hrefs: blocked
+                .iter()
+                .map(|(root, is_dir)| uri::path_to_href(root, *is_dir, &self.mount_prefix))
+                .collect(),
+        })
+    }
+
+    /// 评估 `If` 头：任一列表的全部条件成立即通过，否则返回 412
+    async fn check_if_header(
+        &self,
+        principal: Option<&Principal>,
+        path: &FsPath,
+        headers: &HeaderMap,
+    ) -> Result<(), WebDavError> {
+        let Some(if_header) = IfHeader::from_headers(headers)? else {
+            return Ok(());
+        };
+
+        for list in &if_header.lists {
+            let target = match &list.resource {
+                Some(url) => match uri::href_to_path(url, &self.mount_prefix) {
+                    Some(target) => target,
+                    // 指向其他服务器的资源，条件无法成立
+                    None => continue,
+                },
+                None => path.to_path_buf(),
+            };
+            // 用户无权读取的资源同样视为条件不成立，避免通过 412 探测其存在和 ETag
+            if !self.allows(principal, &target, false) {
+                continue;
+            }
+
+            let needs_etag = list
+                .conditions
+                .iter()
+                .any(|condition| matches!(condition.kind, ConditionKind::ETag(_)));
+            let etag = if needs_etag {
+                match self.backend.get_resource(&target).await {
+                    Ok(resource) => Some(self.visible_metadata(principal, resource.metadata).await.etag),
+                    Err(_) => None,
+                }
+            } else {
+                None
+            };
+            let locks = self.locks.discover(&target);
+
+            let satisfied = list.conditions.iter().all(|condition| {
+                let matched = match &condition.kind {
+                    ConditionKind::StateToken(token) => locks.iter().any(|lock| lock.token == *token),
+                    ConditionKind::ETag(tag) => etag
+                        .as_deref()
+                        .is_some_and(|etag| conditional::etag_list_matches(tag, etag, true)),
+                };
+                matched != condition.not
+            });
+            if satisfied {
+                return Ok(());
+            }
+        }
+
+        Err(WebDavError::PreconditionFailed("If header".to_string()))
     }
 
     pub async fn handle_propfind(
         &self,
         path: Path<String>,
-        _req: Request<Body>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let path = std::path::PathBuf::from(path.0);
+        let path = request_path(path)?;
+        self.check_readable(principal(&req), &path).await?;
+        // 未指定 Depth 时按 infinity 处理
+        let depth = parse_depth(req.headers())?.unwrap_or(Depth::Infinity);
+        if depth == Depth::Infinity && !self.allow_infinite_depth {
+            return Err(WebDavError::Condition {
+                status: StatusCode::FORBIDDEN,
+                condition: "propfind-finite-depth",
+                hrefs: Vec::new(),
+            });
+        }
+
+        let principal = principal(&req).cloned();
+        let body = read_body(req.into_body()).await?;
+        let request = xml::parse_propfind_request(&body)?;
+
         let resource = self.backend.get_resource(&path).await?;
-        
-        let mut resources = vec![resource.metadata];
-        if let Some(children) = resource.children {
-            resources.extend(children);
+        // 只列出用户可以访问的成员，例如家目录模式下根集合中只有自己的家目录
+        let visible = |children: Option<Vec<_>>| {
+            let mut children = children.unwrap_or_default();
+            children.retain(|child: &ResourceMetadata| self.allows(principal.as_ref(), &child.path, false));
+            children
+        };
+
+        // 符号链接可能指向祖先目录，已经展开过的目录不再展开
+        let mut visited: HashSet<_> = resource.metadata.file_id.into_iter().collect();
+        let mut resources = vec![self.visible_metadata(principal.as_ref(), resource.metadata).await];
+        match depth {
+            Depth::Zero => {}
+            Depth::One => resources.extend(visible(resource.children)),
+            Depth::Infinity => {
+                let mut pending: VecDeque<_> = visible(resource.children).into();
+                while let Some(child) = pending.pop_front() {
+                    if child.is_dir && child.file_id.is_none_or(|id| visited.insert(id)) {
+                        let info = self.backend.get_resource(&child.path).await?;
+                        pending.extend(visible(info.children));
+                    }
+                    resources.push(child);
+                }
+            }
+        }
+
+        let mut responses = Vec::with_capacity(resources.len());
+        for resource in &mut resources {
+            // 后端已经随元数据读取了死属性时不再逐个读取
+            let dead = match resource.dead_properties.take() {
+                Some(dead) => dead,
+                None => self.backend.get_properties(&resource.path).await?,
+            };
+            let locks = self.locks.discover(&resource.path);
+            responses.push(props::propfind_response(resource, dead, &locks, &request, &self.mount_prefix));
         }
 
-        let xml_response = xml::create_multistatus_response(&resources)
+        let xml_response = xml::create_multistatus_response(&responses)
             .map_err(|e| WebDavError::Internal(e.to_string()))?;
 
         Ok(Response::builder()
@@ -54,23 +383,107 @@ impl<B: Backend> WebDavHandler<B> {
     pub async fn handle_get(
         &self,
         path: Path<String>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let path = std::path::PathBuf::from(path.0);
+        let path = request_path(path)?;
+        self.check_readable(principal(&req), &path).await?;
         let resource = self.backend.get_resource(&path).await?;
 
         if resource.metadata.is_dir {
             return Err(WebDavError::InvalidInput("Cannot GET a directory".to_string()));
         }
+        conditional::check_preconditions(req.headers(), req.method(), Some(&resource.metadata))?;
 
-        let content = self.backend.read_file(&path).await?;
+        let len = resource.metadata.len;
+        let ranges = range::requested_ranges(req.headers(), &resource.metadata)?;
+        let head_only = req.method() == Method::HEAD;
 
-        Ok(Response::builder()
-            .status(StatusCode::OK)
+        let content_type = resource
+            .metadata
+            .content_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        let builder = Response::builder()
             .header(header::ETAG, &resource.metadata.etag)
-            .header(header::LAST_MODIFIED, resource.metadata.modified.to_rfc2822())
-            .header(header::CONTENT_LENGTH, resource.metadata.len)
-            .body(Body::from(content))
-            .unwrap())
+            .header(header::LAST_MODIFIED, conditional::http_date(&resource.metadata.modified))
+            .header(header::ACCEPT_RANGES, "bytes");
+        let builder = match &resource.metadata.content_language {
+            Some(language) => builder.header(header::CONTENT_LANGUAGE, language),
+            None => builder,
+        };
+
+        let response = match ranges {
+            None => {
+                let content = self.file_content(&path, None, head_only).await?;
+                builder
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_LENGTH, len)
+                    .body(Body::from_stream(content))
+            }
+            Some(ranges) if ranges.len() == 1 => {
+                let range = &ranges[0];
+                let content = self.file_content(&path, Some(range.clone()), head_only).await?;
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_RANGE, range::content_range(range, len))
+                    .header(header::CONTENT_LENGTH, range.end - range.start)
+                    .body(Body::from_stream(content))
+            }
+            Some(ranges) => {
+                // 每个区间依次输出：分隔头、区间内容、换行，最后是结束分隔符
+                let boundary = Uuid::new_v4().simple().to_string();
+                let mut parts: Vec<ByteStream> = Vec::with_capacity(ranges.len() * 3 + 1);
+                let mut content_length = 0;
+                for range in ranges {
+                    let head = format!(
+                        "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                        boundary,
+                        content_type,
+                        range::content_range(&range, len)
+                    );
+                    content_length += head.len() as u64 + (range.end - range.start) + 2;
+                    parts.push(Box::pin(stream::once(async move { Ok(Bytes::from(head)) })));
+                    parts.push(self.file_content(&path, Some(range), head_only).await?);
+                    parts.push(Box::pin(stream::once(async { Ok(Bytes::from_static(b"\r\n")) })));
+                }
+                let tail = format!("--{}--\r\n", boundary);
+                content_length += tail.len() as u64;
+                parts.push(Box::pin(stream::once(async move { Ok(Bytes::from(tail)) })));
+
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_TYPE, format!("multipart/byteranges; boundary={}", boundary))
+                    .header(header::CONTENT_LENGTH, content_length)
+                    .body(Body::from_stream(stream::iter(parts).flatten()))
+            }
+        };
+
+        // 响应头中含有客户端保存的属性值，构造失败时返回错误而不是 panic
+        response.map_err(|e| WebDavError::Internal(e.to_string()))
+    }
+
+    /// 与 GET 返回相同的响应头，但不通过后端读取文件内容
+    pub async fn handle_head(
+        &self,
+        path: Path<String>,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, WebDavError> {
+        self.handle_get(path, req).await
+    }
+
+    /// GET 读取文件内容，HEAD 只需要长度，返回空流
+    async fn file_content(
+        &self,
+        path: &FsPath,
+        range: Option<std::ops::Range<u64>>,
+        head_only: bool,
+    ) -> Result<ByteStream, WebDavError> {
+        if head_only {
+            return Ok(Box::pin(stream::empty()));
+        }
+        self.backend.read_file(path, range).await
     }
 
     pub async fn handle_put(
@@ -78,21 +491,44 @@ impl<B: Backend> WebDavHandler<B> {
         path: Path<String>,
         req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let path = std::path::PathBuf::from(path.0);
-        
-        let body = req.into_body();
-        let mut bytes = Vec::new();
-        let mut stream = StreamReader::new(
-            body.into_data_stream().map(|r| r.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)))
-        );
-        tokio::io::copy(&mut stream, &mut bytes).await
-            .map_err(|e| WebDavError::Internal(e.to_string()))?;
+        let path = request_path(path)?;
+        self.check_writable(principal(&req), &path).await?;
+        // 否则两个 `If-None-Match: *` 的 PUT 可以同时通过检查，后完成的覆盖先完成的
+        let _guard = self.put_locks.lock(&path).await;
+        let existing = self.backend.get_resource(&path).await.ok().map(|r| r.metadata);
+        conditional::check_preconditions(req.headers(), req.method(), existing.as_ref())?;
+        self.check_if_header(principal(&req), &path, req.headers()).await?;
+        self.check_locks(principal(&req), &path, req.headers(), false, existing.is_none())?;
+
+        let limit = self.max_upload_size.unwrap_or(u64::MAX);
+        let declared = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > limit) {
+            return Err(WebDavError::PayloadTooLarge(limit));
+        }
 
-        let content = Bytes::from(bytes);
-        self.backend.write_file(&path, content).await?;
+        // 没有 Content-Length（分块传输）时边接收边计数，超过上限后中止写入
+        let mut received = 0u64;
+        let content = req.into_body().into_data_stream().map(move |chunk| {
+            let chunk = chunk.map_err(std::io::Error::other)?;
+            received += chunk.len() as u64;
+            if received > limit {
+                return Err(std::io::Error::from(std::io::ErrorKind::FileTooLarge));
+            }
+            Ok(chunk)
+        });
+        let created = match self.backend.write_file(&path, Box::pin(content)).await {
+            Err(WebDavError::Io(e)) if e.kind() == std::io::ErrorKind::FileTooLarge => {
+                return Err(WebDavError::PayloadTooLarge(limit));
+            }
+            result => result?,
+        };
 
         Ok(Response::builder()
-            .status(StatusCode::CREATED)
+            .status(if created { StatusCode::CREATED } else { StatusCode::NO_CONTENT })
             .body(Body::empty())
             .unwrap())
     }
@@ -100,8 +536,12 @@ impl<B: Backend> WebDavHandler<B> {
     pub async fn handle_mkcol(
         &self,
         path: Path<String>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let path = std::path::PathBuf::from(path.0);
+        let path = request_path(path)?;
+        self.check_writable(principal(&req), &path).await?;
+        self.check_if_header(principal(&req), &path, req.headers()).await?;
+        self.check_locks(principal(&req), &path, req.headers(), false, true)?;
         // 判断是否存在应该交给实现判断
         self.backend.create_dir(&path).await?;
 
@@ -114,10 +554,20 @@ impl<B: Backend> WebDavHandler<B> {
     pub async fn handle_delete(
         &self,
         path: Path<String>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let path = std::path::PathBuf::from(path.0);
-    
-        self.backend.delete(&path).await?;
+        let path = request_path(path)?;
+        self.check_writable(principal(&req), &path).await?;
+        self.check_removable(principal(&req), &path)?;
+        let resource = self.backend.get_resource(&path).await?;
+        conditional::check_preconditions(req.headers(), req.method(), Some(&resource.metadata))?;
+        self.check_if_header(principal(&req), &path, req.headers()).await?;
+        self.check_locks(principal(&req), &path, req.headers(), true, true)?;
+
+        if let Err(e) = self.backend.delete(&path).await {
+            return self.member_failures(e);
+        }
+        self.locks.remove_tree(&path);
 
         Ok(Response::builder()
             .status(StatusCode::NO_CONTENT)
@@ -130,50 +580,447 @@ impl<B: Backend> WebDavHandler<B> {
         path: Path<String>,
         req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let from = std::path::PathBuf::from(path.0);
-        let destination = req
-            .headers()
-            .get(DESTINATION)
-            .ok_or_else(|| WebDavError::InvalidInput("Destination header required".to_string()))?
-            .to_str()
-            .map_err(|_| WebDavError::InvalidInput("Invalid destination header".to_string()))?;
+        let from = request_path(path)?;
+        let to = parse_destination(&req, &self.mount_prefix)?;
+        self.check_readable(principal(&req), &from).await?;
+        self.check_writable(principal(&req), &to).await?;
+        self.check_removable(principal(&req), &to)?;
+        let overwrite = parse_overwrite(req.headers())?;
+        // COPY 只支持 Depth 为 0 或 infinity，默认 infinity
+        let depth = match parse_depth(req.headers())? {
+            None | Some(Depth::Infinity) => Depth::Infinity,
+            Some(Depth::Zero) => Depth::Zero,
+            Some(Depth::One) => {
+                return Err(WebDavError::InvalidInput(
+                    "Depth: 1 is not allowed for COPY".to_string(),
+                ))
+            }
+        };
+
+        let resource = self.backend.get_resource(&from).await?;
+        check_transfer_paths(&from, &to)?;
+        conditional::check_preconditions(req.headers(), req.method(), Some(&resource.metadata))?;
+        self.check_if_header(principal(&req), &from, req.headers()).await?;
+        self.check_locks(principal(&req), &to, req.headers(), true, true)?;
 
-        let to = std::path::PathBuf::from(destination);
+        let existed = match self.prepare_destination(&to, overwrite).await {
+            Ok(existed) => existed,
+            Err(e) => return self.member_failures(e),
+        };
+        if let Err(e) = self.backend.copy(&from, &to, depth).await {
+            return self.member_failures(e);
+        }
+
+        Ok(transfer_response(existed))
+    }
 
-        self.backend.copy(&from, &to).await?;
+    pub async fn handle_move(
+        &self,
+        path: Path<String>,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, WebDavError> {
+        let from = request_path(path)?;
+        let to = parse_destination(&req, &self.mount_prefix)?;
+        self.check_writable(principal(&req), &from).await?;
+        self.check_writable(principal(&req), &to).await?;
+        self.check_removable(principal(&req), &from)?;
+        self.check_removable(principal(&req), &to)?;
+        let overwrite = parse_overwrite(req.headers())?;
+
+        let resource = self.backend.get_resource(&from).await?;
+        // 集合的 MOVE 必须作用于整棵子树
+        if resource.metadata.is_dir
+            && !matches!(parse_depth(req.headers())?, None | Some(Depth::Infinity))
+        {
+            return Err(WebDavError::InvalidInput(
+                "MOVE of a collection requires Depth: infinity".to_string(),
+            ));
+        }
+        check_transfer_paths(&from, &to)?;
+        conditional::check_preconditions(req.headers(), req.method(), Some(&resource.metadata))?;
+        self.check_if_header(principal(&req), &from, req.headers()).await?;
+        self.check_locks(principal(&req), &from, req.headers(), true, true)?;
+        self.check_locks(principal(&req), &to, req.headers(), true, true)?;
+
+        let existed = match self.prepare_destination(&to, overwrite).await {
+            Ok(existed) => existed,
+            Err(e) => return self.member_failures(e),
+        };
+        if let Err(e) = self.backend.move_resource(&from, &to).await {
+            return self.member_failures(e);
+        }
+        self.locks.remove_tree(&from);
+
+        Ok(transfer_response(existed))
+    }
+
+    /// 集合操作部分成员失败时返回列出这些成员的 207，其余错误原样返回
+    fn member_failures(&self, error: WebDavError) -> Result<Response<Body>, WebDavError> {
+        let WebDavError::Multistatus(members) = error else {
+            return Err(error);
+        };
+        let responses: Vec<_> = members
+            .iter()
+            .map(|member| xml::StatusResponse {
+                href: uri::path_to_href(&member.path, member.is_dir, &self.mount_prefix),
+                status: member.status,
+            })
+            .collect();
+        let body = xml::create_status_multistatus_response(&responses)
+            .map_err(|e| WebDavError::Internal(e.to_string()))?;
 
         Ok(Response::builder()
-            .status(StatusCode::CREATED)
-            .body(Body::empty())
+            .status(StatusCode::MULTI_STATUS)
+            .header(header::CONTENT_TYPE, "application/xml")
+            .body(Body::from(body))
             .unwrap())
     }
 
-    pub async fn handle_move(
+    /// 处理 COPY/MOVE 的已存在目标：`Overwrite: F` 时返回 412，否则先删除目标。
+    ///
+    /// 返回目标原先是否存在。
+    async fn prepare_destination(&self, to: &FsPath, overwrite: bool) -> Result<bool, WebDavError> {
+        match self.backend.get_resource(to).await {
+            Ok(_) if !overwrite => Err(WebDavError::PreconditionFailed("Overwrite".to_string())),
+            Ok(_) => {
+                self.backend.delete(to).await?;
+                self.locks.remove_tree(to);
+                Ok(true)
+            }
+            Err(WebDavError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn handle_proppatch(
+        &self,
+        path: Path<String>,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, WebDavError> {
+        let path = request_path(path)?;
+        self.check_writable(principal(&req), &path).await?;
+        let resource = self.backend.get_resource(&path).await?;
+        conditional::check_preconditions(req.headers(), req.method(), Some(&resource.metadata))?;
+        self.check_if_header(principal(&req), &path, req.headers()).await?;
+        self.check_locks(principal(&req), &path, req.headers(), false, false)?;
+
+        let body = read_body(req.into_body()).await?;
+        let updates = xml::parse_proppatch_request(&body)?;
+
+        let names: Vec<_> = updates
+            .iter()
+            .map(|update| match update {
+                PropertyUpdate::Set(prop) => PropName::new(&prop.namespace, &prop.name),
+                PropertyUpdate::Remove { namespace, name } => PropName::new(namespace, name),
+            })
+            .collect();
+
+        // 修改必须全部成功或全部失败：任一属性被拒绝时，其余属性返回 424
+        let rejections: Vec<_> = names
+            .iter()
+            .zip(&updates)
+            .map(|(name, update)| props::rejection(name, update))
+            .collect();
+        let results = if rejections.iter().any(Option::is_some) {
+            names
+                .into_iter()
+                .zip(rejections)
+                .map(|(name, status)| (name, status.unwrap_or(StatusCode::FAILED_DEPENDENCY)))
+                .collect()
+        } else {
+            let status = match self.backend.patch_properties(&path, &updates).await {
+                Ok(()) => StatusCode::OK,
+                Err(e) => e.status_code(),
+            };
+            names.into_iter().map(|name| (name, status)).collect()
+        };
+
+        let response = xml::PropResponse {
+            href: uri::path_to_href(&path, resource.metadata.is_dir, &self.mount_prefix),
+            propstats: props::group_propstats(results),
+        };
+        let xml_response = xml::create_multistatus_response(&[response])
+            .map_err(|e| WebDavError::Internal(e.to_string()))?;
+
+        Ok(Response::builder()
+            .status(StatusCode::MULTI_STATUS)
+            .header(header::CONTENT_TYPE, "application/xml")
+            .body(Body::from(xml_response))
+            .unwrap())
+    }
+
+    pub async fn handle_lock(
+        &self,
+        path: Path<String>,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, WebDavError> {
+        let path = request_path(path)?;
+        let principal = principal(&req).cloned();
+        self.check_writable(principal.as_ref(), &path).await?;
+        let (parts, body) = req.into_parts();
+        let body = read_body(body).await?;
+        let timeout = parse_timeout(&parts.headers)?;
+        self.check_if_header(principal.as_ref(), &path, &parts.headers).await?;
+
+        // 空请求体表示刷新已有的锁
+        if body.is_empty() {
+            let owner = principal.as_ref().map(|principal| principal.name.as_str());
+            let tokens = IfHeader::from_headers(&parts.headers)?
+                .map(|if_header| if_header.tokens())
+                .unwrap_or_default();
+            let lock = tokens
+                .iter()
+                .find_map(|token| self.locks.refresh(&path, token, owner, timeout).ok())
+                .ok_or_else(|| WebDavError::PreconditionFailed("No lock token to refresh".to_string()))?;
+            let xml_response = xml::create_lockdiscovery_response(&[lock], &self.mount_prefix)
+                .map_err(|e| WebDavError::Internal(e.to_string()))?;
+            return Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "application/xml")
+                .body(Body::from(xml_response))
+                .unwrap());
+        }
+
+        let info = xml::parse_lockinfo(&body)?;
+        let depth = match parse_depth(&parts.headers)? {
+            None | Some(Depth::Infinity) => Depth::Infinity,
+            Some(Depth::Zero) => Depth::Zero,
+            Some(Depth::One) => {
+                return Err(WebDavError::InvalidInput("Depth: 1 is not allowed for LOCK".to_string()))
+            }
+        };
+
+        let existing = self.backend.get_resource(&path).await.ok();
+        let exists = existing.is_some();
+        if !exists {
+            self.check_locks(principal.as_ref(), &path, &parts.headers, false, true)?;
+        }
+
+        // 先取得锁再创建文件，锁冲突时不会留下空文件
+        let is_dir = existing.is_some_and(|resource| resource.metadata.is_dir);
+        let owner = principal.as_ref().map(|principal| principal.name.as_str());
+        let lock = self.locks.lock(&path, is_dir, owner, info, depth, timeout)?;
+
+        // 锁定不存在的资源时创建一个空文件
+        if !exists {
+            if let Err(e) = self.backend.write_file(&path, Box::pin(stream::empty())).await {
+                let _ = self.locks.unlock(&path, &lock.token, owner);
+                return Err(e);
+            }
+        }
+        let xml_response = xml::create_lockdiscovery_response(std::slice::from_ref(&lock), &self.mount_prefix)
+            .map_err(|e| WebDavError::Internal(e.to_string()))?;
+
+        Ok(Response::builder()
+            .status(if exists { StatusCode::OK } else { StatusCode::CREATED })
+            .header(header::CONTENT_TYPE, "application/xml")
+            .header("Lock-Token", format!("<{}>", lock.token))
+            .body(Body::from(xml_response))
+            .unwrap())
+    }
+
+    pub async fn handle_unlock(
         &self,
         path: Path<String>,
         req: Request<Body>,
     ) -> Result<Response<Body>, WebDavError> {
-        let from = std::path::PathBuf::from(path.0);
-        let destination = req
+        let path = request_path(path)?;
+        self.check_writable(principal(&req), &path).await?;
+        let token = req
             .headers()
-            .get(DESTINATION)
-            .ok_or_else(|| WebDavError::InvalidInput("Destination header required".to_string()))?
+            .get(LOCK_TOKEN)
+            .ok_or_else(|| WebDavError::InvalidInput("Lock-Token header required".to_string()))?
             .to_str()
-            .map_err(|_| WebDavError::InvalidInput("Invalid destination header".to_string()))?;
+            .map_err(|_| WebDavError::InvalidInput("Invalid Lock-Token header".to_string()))?
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
 
-        let to = std::path::PathBuf::from(destination);
-
-        self.backend.move_resource(&from, &to).await?;
+        self.locks.unlock(&path, token, principal(&req).map(|principal| principal.name.as_str()))?;
 
         Ok(Response::builder()
-            .status(StatusCode::CREATED)
+            .status(StatusCode::NO_CONTENT)
             .body(Body::empty())
             .unwrap())
     }
 }
 
+/// 读取 PROPFIND、PROPPATCH 和 LOCK 的 XML 请求体，超过 [`MAX_XML_BODY`] 时返回 413
+async fn read_body(body: Body) -> Result<Bytes, WebDavError> {
+    axum::body::to_bytes(body, MAX_XML_BODY).await.map_err(|e| {
+        let too_large = std::error::Error::source(&e)
+            .is_some_and(|source| source.is::<http_body_util::LengthLimitError>());
+        if too_large {
+            WebDavError::PayloadTooLarge(MAX_XML_BODY as u64)
+        } else {
+            WebDavError::InvalidInput(e.to_string())
+        }
+    })
+}
+
+fn parse_depth(headers: &HeaderMap) -> Result<Option<Depth>, WebDavError> {
+    let Some(value) = headers.get(DEPTH) else {
+        return Ok(None);
+    };
+    match value.to_str().map(str::trim) {
+        Ok("0") => Ok(Some(Depth::Zero)),
+        Ok("1") => Ok(Some(Depth::One)),
+        Ok(v) if v.eq_ignore_ascii_case("infinity") => Ok(Some(Depth::Infinity)),
+        _ => Err(WebDavError::InvalidInput("Invalid Depth header".to_string())),
+    }
+}
+
+/// 解析 `Timeout: Second-3600, Infinite`，取第一个可识别的值
+fn parse_timeout(headers: &HeaderMap) -> Result<Duration, WebDavError> {
+    let Some(value) = headers.get(TIMEOUT) else {
+        return Ok(DEFAULT_LOCK_TIMEOUT);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| WebDavError::InvalidInput("Invalid Timeout header".to_string()))?;
+
+    for item in value.split(',').map(str::trim) {
+        if item.eq_ignore_ascii_case("infinite") {
+            return Ok(MAX_LOCK_TIMEOUT);
+        }
+        if let Some(seconds) = item.strip_prefix("Second-").and_then(|s| s.parse::<u64>().ok()) {
+            return Ok(Duration::from_secs(seconds).min(MAX_LOCK_TIMEOUT));
+        }
+    }
+    Ok(DEFAULT_LOCK_TIMEOUT)
+}
+
+/// 按路径分配的异步互斥锁，不再使用的锁会被清理
+#[derive(Default)]
+struct PathLocks {
+    locks: Mutex<HashMap<PathBuf, Weak<tokio::sync::Mutex<()>>>>,
+}
+
+impl PathLocks {
+    async fn lock(&self, path: &FsPath) -> tokio::sync::OwnedMutexGuard<()> {
+        let mutex = {
+            let mut locks = self.locks.lock().unwrap();
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(path).and_then(Weak::upgrade) {
+                Some(mutex) => mutex,
+                None => {
+                    let mutex = Arc::new(tokio::sync::Mutex::new(()));
+                    locks.insert(path.to_path_buf(), Arc::downgrade(&mutex));
+                    mutex
+                }
+            }
+        };
+        mutex.lock_owned().await
+    }
+}
+
+/// 认证中间件放入请求的用户，未启用认证时为 `None`
+fn principal(req: &Request<Body>) -> Option<&Principal> {
+    req.extensions().get::<Principal>()
+}
+
+/// 用户名能否作为家目录名：单个路径段，不含分隔符和 NUL
+fn is_home_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..") && !name.contains(['/', '\\', '\0'])
+}
+
+/// 对路由传入的原始请求路径进行百分号解码和规范化
+fn request_path(path: Path<String>) -> Result<PathBuf, WebDavError> {
+    uri::decode_path(&path.0)
+        .ok_or_else(|| WebDavError::InvalidInput(format!("Invalid request path: {}", path.0)))
+}
+
+/// 解析 `Destination` 头，指向其他服务器或挂载点之外的地址返回 502
+fn parse_destination(req: &Request<Body>, mount_prefix: &str) -> Result<PathBuf, WebDavError> {
+    let destination = req
+        .headers()
+        .get(DESTINATION)
+        .ok_or_else(|| WebDavError::InvalidInput("Destination header required".to_string()))?
+        .to_str()
+        .map_err(|_| WebDavError::InvalidInput("Invalid destination header".to_string()))?;
+
+    let bad_gateway = || WebDavError::BadGateway(destination.to_string());
+    if let (Some(scheme), Some(authority), _) = uri::split_uri(destination) {
+        // HTTP/2 请求没有 Host 头，authority 在请求 URI 中
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+            .ok_or_else(bad_gateway)?;
+        if !uri::same_authority(scheme, authority, host) {
+            return Err(bad_gateway());
+        }
+    }
+    uri::href_to_path(destination, mount_prefix).ok_or_else(bad_gateway)
+}
+
+/// 解析 `Overwrite` 头，缺省为 `T`
+fn parse_overwrite(headers: &HeaderMap) -> Result<bool, WebDavError> {
+    match headers.get(OVERWRITE).map(|v| v.as_bytes()) {
+        None | Some(b"T") | Some(b"t") => Ok(true),
+        Some(b"F") | Some(b"f") => Ok(false),
+        Some(_) => Err(WebDavError::InvalidInput("Invalid Overwrite header".to_string())),
+    }
+}
+
+/// 源与目标相同返回 403，二者互相包含（覆盖会删除源或递归复制自身）返回 409
+fn check_transfer_paths(from: &FsPath, to: &FsPath) -> Result<(), WebDavError> {
+    if from == to {
+        return Err(WebDavError::PermissionDenied(to.to_path_buf()));
+    }
+    if to.starts_with(from) || from.starts_with(to) {
+        return Err(WebDavError::Conflict(format!(
+            "Destination {} overlaps the source",
+            to.display()
+        )));
+    }
+    Ok(())
+}
+
+/// 目标原先存在时返回 204，否则返回 201
+fn transfer_response(existed: bool) -> Response<Body> {
+    let status = if existed { StatusCode::NO_CONTENT } else { StatusCode::CREATED };
+    Response::builder().status(status).body(Body::empty()).unwrap()
+}
+
 impl IntoResponse for WebDavError {
     fn into_response(self) -> Response {
+        if let WebDavError::RangeNotSatisfiable(len) = &self {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .header(header::ACCEPT_RANGES, "bytes")
+                .body(Body::empty())
+                .unwrap();
+        }
+
+        if let WebDavError::NotModified(etag) = &self {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, etag)
+                .body(Body::empty())
+                .unwrap();
+        }
+
+        if let WebDavError::Unauthorized(challenges) = &self {
+            let mut response = Response::builder().status(StatusCode::UNAUTHORIZED);
+            for challenge in challenges {
+                response = response.header(header::WWW_AUTHENTICATE, challenge);
+            }
+            return response.body(Body::from(self.to_string())).unwrap();
+        }
+
+        if let WebDavError::Condition { status, condition, hrefs } = &self {
+            if let Ok(body) = xml::create_error_response(condition, hrefs) {
+                return Response::builder()
+                    .status(*status)
+                    .header(header::CONTENT_TYPE, "application/xml")
+                    .body(Body::from(body))
+                    .unwrap();
+            }
+        }
+
         Response::builder()
             .status(self.status_code())
             .body(Body::from(self.to_string()))
@@ -0,0 +1,209 @@
+use axum::http::{HeaderValue, StatusCode};
+use chrono::SecondsFormat;
+
+use crate::{
+    backend::{DeadProperty, PropertyUpdate, ResourceMetadata},
+    handler::{conditional, uri},
+    lock::ActiveLock,
+    xml::{self, PropName, PropResponse, PropValue, Propstat, PropfindRequest},
+};
+
+/// 由服务器计算、客户端不能通过 PROPPATCH 修改的 DAV: 属性
+const PROTECTED_PROPERTIES: &[&str] = &[
+    "resourcetype",
+    "getcontentlength",
+    "getlastmodified",
+    "getetag",
+    "creationdate",
+    "lockdiscovery",
+    "supportedlock",
+];
+
+pub(crate) fn is_protected(name: &PropName) -> bool {
+    name.is_dav() && PROTECTED_PROPERTIES.contains(&name.name.as_str())
+}
+
+/// PROPPATCH 中不能执行的修改及其状态码：活属性返回 403，
+/// 会被用作响应头的属性值无效时返回 409
+pub(crate) fn rejection(name: &PropName, update: &PropertyUpdate) -> Option<StatusCode> {
+    if is_protected(name) {
+        return Some(StatusCode::FORBIDDEN);
+    }
+    let PropertyUpdate::Set(prop) = update else {
+        return None;
+    };
+    let valid = match (name.is_dav(), name.name.as_str()) {
+        (true, "getcontenttype") => header_text(&prop.value).is_some_and(|value| {
+            value.is_empty() || value.parse::<mime_guess::mime::Mime>().is_ok()
+        }),
+        (true, "getcontentlanguage") => header_text(&prop.value).is_some_and(|value| {
+            value.is_empty() || value.split(',').all(|tag| is_language_tag(tag.trim()))
+        }),
+        _ => true,
+    };
+    (!valid).then_some(StatusCode::CONFLICT)
+}
+
+/// 简化的 BCP 47 语言标签检查，例如 `en`、`zh-Hans-CN`
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (1..=8).contains(&primary.len())
+        && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+/// 属性的文本值，不能作为 HTTP 头的值时返回 `None`
+fn header_text(value: &str) -> Option<String> {
+    let value = quick_xml::escape::unescape(value.trim()).ok()?;
+    HeaderValue::from_str(&value).ok()?;
+    Some(value.into_owned())
+}
+
+/// 支持的锁类型：排他和共享的写锁
+const SUPPORTED_LOCKS: &str = "<D:lockentry><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>\
+<D:lockentry><D:lockscope><D:shared/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>";
+
+/// 服务器维护的活属性（live properties）
+fn live_properties(
+    resource: &ResourceMetadata,
+    locks: &[ActiveLock],
+    mount_prefix: &str,
+) -> Vec<(PropName, PropValue)> {
+    let mut props = Vec::new();
+
+    let resourcetype = if resource.is_dir {
+        PropValue::Xml("<D:collection/>".to_string())
+    } else {
+        PropValue::Empty
+    };
+    props.push((PropName::dav("resourcetype"), resourcetype));
+
+    // RFC 4918 要求 creationdate 使用 RFC 3339 格式，文件系统不提供创建时间时使用修改时间
+    let created = resource.created.unwrap_or(resource.modified);
+    props.push((
+        PropName::dav("creationdate"),
+        PropValue::Text(created.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    ));
+
+    if let Some(name) = resource.path.file_name() {
+        props.push((
+            PropName::dav("displayname"),
+            PropValue::Text(name.to_string_lossy().into_owned()),
+        ));
+    }
+
+    if !resource.is_dir {
+        props.push((
+            PropName::dav("getcontentlength"),
+            PropValue::Text(resource.len.to_string()),
+        ));
+    }
+
+    if let Some(content_type) = &resource.content_type {
+        props.push((
+            PropName::dav("getcontenttype"),
+            PropValue::Text(content_type.clone()),
+        ));
+    }
+
+    if let Some(language) = &resource.content_language {
+        props.push((
+            PropName::dav("getcontentlanguage"),
+            PropValue::Text(language.clone()),
+        ));
+    }
+
+    props.push((
+        PropName::dav("getlastmodified"),
+        PropValue::Text(conditional::http_date(&resource.modified)),
+    ));
+    props.push((PropName::dav("getetag"), PropValue::Text(resource.etag.clone())));
+    props.push((
+        PropName::dav("supportedlock"),
+        PropValue::Xml(SUPPORTED_LOCKS.to_string()),
+    ));
+
+    let lockdiscovery = match xml::create_activelocks(locks, mount_prefix) {
+        Ok(activelocks) if !activelocks.is_empty() => PropValue::Xml(activelocks),
+        _ => PropValue::Empty,
+    };
+    props.push((PropName::dav("lockdiscovery"), lockdiscovery));
+
+    props
+}
+
+/// 根据 PROPFIND 请求构造一个资源的响应，未知属性归入 404 的 propstat
+pub(crate) fn propfind_response(
+    resource: &ResourceMetadata,
+    dead: Vec<DeadProperty>,
+    locks: &[ActiveLock],
+    request: &PropfindRequest,
+    mount_prefix: &str,
+) -> PropResponse {
+    let mut available = live_properties(resource, locks, mount_prefix);
+    // 客户端保存的同名死属性（例如 displayname）覆盖计算出的值
+    for prop in dead {
+        let name = PropName::new(prop.namespace, prop.name);
+        match available.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, value)) => *value = PropValue::Xml(prop.value),
+            None => available.push((name, PropValue::Xml(prop.value))),
+        }
+    }
+
+    let (found, missing) = match request {
+        PropfindRequest::PropName => {
+            for (_, value) in available.iter_mut() {
+                *value = PropValue::Empty;
+            }
+            (available, Vec::new())
+        }
+        PropfindRequest::AllProp(include) => {
+            let missing = include
+                .iter()
+                .filter(|name| !available.iter().any(|(n, _)| n == *name))
+                .map(|name| (name.clone(), PropValue::Empty))
+                .collect();
+            (available, missing)
+        }
+        PropfindRequest::Prop(names) => {
+            let mut found = Vec::new();
+            let mut missing = Vec::new();
+            for name in names {
+                match available.iter().position(|(n, _)| n == name) {
+                    Some(index) => found.push(available.swap_remove(index)),
+                    None => missing.push((name.clone(), PropValue::Empty)),
+                }
+            }
+            (found, missing)
+        }
+    };
+
+    let mut propstats = Vec::new();
+    if !found.is_empty() {
+        propstats.push(Propstat { status: StatusCode::OK, props: found });
+    }
+    if !missing.is_empty() {
+        propstats.push(Propstat { status: StatusCode::NOT_FOUND, props: missing });
+    }
+
+    PropResponse {
+        href: uri::path_to_href(&resource.path, resource.is_dir, mount_prefix),
+        propstats,
+    }
+}
+
+/// 按状态码对属性名分组，生成 PROPPATCH 的 propstat 列表
+pub(crate) fn group_propstats(results: Vec<(PropName, StatusCode)>) -> Vec<Propstat> {
+    let mut propstats: Vec<Propstat> = Vec::new();
+    for (name, status) in results {
+        match propstats.iter_mut().find(|p| p.status == status) {
+            Some(propstat) => propstat.props.push((name, PropValue::Empty)),
+            None => propstats.push(Propstat {
+                status,
+                props: vec![(name, PropValue::Empty)],
+            }),
+        }
+    }
+    propstats
+}
@@ -0,0 +1,214 @@
+use std::ops::Range;
+
+use axum::http::{header, HeaderMap};
+use chrono::DateTime;
+
+use crate::{backend::ResourceMetadata, error::WebDavError};
+
+use super::conditional;
+
+/// 单个请求中允许的最大区间数，超过时忽略 Range 头返回完整内容
+const MAX_RANGES: usize = 64;
+
+/// 解析 GET 请求的 `Range` 头（RFC 7233），返回需要发送的字节区间。
+///
+/// 返回 `Ok(None)` 表示发送完整内容：没有 Range 头、单位不是 bytes、语法无效，
+/// 或者 `If-Range` 不成立。所有区间都无法满足时返回 `RangeNotSatisfiable`（416）。
+pub(crate) fn requested_ranges(
+    headers: &HeaderMap,
+    resource: &ResourceMetadata,
+) -> Result<Option<Vec<Range<u64>>>, WebDavError> {
+    let Some(value) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return Ok(None);
+    };
+    if !if_range_matches(headers, resource) {
+        return Ok(None);
+    }
+    let Some(specs) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+
+    let len = resource.len;
+    let mut ranges = Vec::new();
+    let mut count = 0;
+    for spec in specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+        count += 1;
+        let Some((start, end)) = spec.split_once('-') else {
+            return Ok(None);
+        };
+        let (start, end) = (start.trim(), end.trim());
+
+        let range = if start.is_empty() {
+            // 后缀区间：最后 N 个字节
+            let Ok(suffix) = end.parse::<u64>() else {
+                return Ok(None);
+            };
+            (suffix > 0 && len > 0).then(|| len.saturating_sub(suffix)..len)
+        } else {
+            let Ok(first) = start.parse::<u64>() else {
+                return Ok(None);
+            };
+            let last = if end.is_empty() {
+                u64::MAX
+            } else {
+                match end.parse::<u64>() {
+                    Ok(last) if last >= first => last,
+                    _ => return Ok(None),
+                }
+            };
+            (first < len).then(|| first..last.saturating_add(1).min(len))
+        };
+        ranges.extend(range);
+    }
+
+    if count == 0 || count > MAX_RANGES {
+        return Ok(None);
+    }
+    if ranges.is_empty() {
+        return Err(WebDavError::RangeNotSatisfiable(len));
+    }
+    Ok(Some(ranges))
+}
+
+/// `If-Range` 使用强比较的 ETag 或与最后修改时间完全相同的日期
+fn if_range_matches(headers: &HeaderMap, resource: &ResourceMetadata) -> bool {
+    let Some(value) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let value = value.trim();
+    if value.starts_with('"') || value.starts_with("W/") {
+        return conditional::etag_list_matches(value, &resource.etag, true);
+    }
+    DateTime::parse_from_rfc2822(value)
+        .is_ok_and(|date| date.timestamp() == resource.modified.timestamp())
+}
+
+pub(crate) fn content_range(range: &Range<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{}", range.start, range.end - 1, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn resource(len: u64) -> ResourceMetadata {
+        ResourceMetadata {
+            path: PathBuf::from("file.txt"),
+            is_dir: false,
+            len,
+            modified: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            created: None,
+            etag: "\"abc\"".to_string(),
+            content_type: None,
+            content_language: None,
+            file_id: None,
+            dead_properties: None,
+        }
+    }
+
+    /// 区间以 `(start, end)` 表示，便于比较
+    fn bounds(ranges: Option<Vec<Range<u64>>>) -> Option<Vec<(u64, u64)>> {
+        ranges.map(|ranges| ranges.into_iter().map(|range| (range.start, range.end)).collect())
+    }
+
+    fn ranges(range: &str, len: u64) -> Result<Option<Vec<(u64, u64)>>, WebDavError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, range.parse().unwrap());
+        requested_ranges(&headers, &resource(len)).map(bounds)
+    }
+
+    fn with_if_range(if_range: &str) -> Option<Vec<(u64, u64)>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=0-9".parse().unwrap());
+        headers.insert(header::IF_RANGE, if_range.parse().unwrap());
+        bounds(requested_ranges(&headers, &resource(100)).unwrap())
+    }
+
+    #[test]
+    fn no_range_header() {
+        assert!(requested_ranges(&HeaderMap::new(), &resource(100)).unwrap().is_none());
+    }
+
+    #[test]
+    fn closed_ranges() {
+        assert_eq!(ranges("bytes=0-9", 100).unwrap(), Some(vec![(0, 10)]));
+        assert_eq!(ranges("bytes=0-0, 50-59", 100).unwrap(), Some(vec![(0, 1), (50, 60)]));
+        // 结束位置超出文件时截断到文件末尾
+        assert_eq!(ranges("bytes=90-200", 100).unwrap(), Some(vec![(90, 100)]));
+    }
+
+    #[test]
+    fn open_ended() {
+        assert_eq!(ranges("bytes=95-", 100).unwrap(), Some(vec![(95, 100)]));
+        assert_eq!(ranges("bytes=0-", 100).unwrap(), Some(vec![(0, 100)]));
+    }
+
+    #[test]
+    fn suffix() {
+        assert_eq!(ranges("bytes=-10", 100).unwrap(), Some(vec![(90, 100)]));
+        // 后缀长于文件时返回整个文件
+        assert_eq!(ranges("bytes=-500", 100).unwrap(), Some(vec![(0, 100)]));
+    }
+
+    #[test]
+    fn past_eof_is_unsatisfiable() {
+        assert!(matches!(ranges("bytes=100-", 100), Err(WebDavError::RangeNotSatisfiable(100))));
+        assert!(matches!(ranges("bytes=200-300", 100), Err(WebDavError::RangeNotSatisfiable(100))));
+        assert!(matches!(ranges("bytes=-0", 100), Err(WebDavError::RangeNotSatisfiable(100))));
+        assert!(matches!(ranges("bytes=0-", 0), Err(WebDavError::RangeNotSatisfiable(0))));
+        assert!(matches!(ranges("bytes=-5", 0), Err(WebDavError::RangeNotSatisfiable(0))));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_dropped() {
+        assert_eq!(ranges("bytes=200-300, 0-4", 100).unwrap(), Some(vec![(0, 5)]));
+    }
+
+    #[test]
+    fn invalid_syntax_is_ignored() {
+        for range in [
+            "bytes=",
+            "bytes=abc",
+            "bytes=5-1",
+            "bytes=1-x",
+            "bytes=--5",
+            "items=0-9",
+            "0-9",
+        ] {
+            assert_eq!(ranges(range, 100).unwrap(), None, "{:?}", range);
+        }
+    }
+
+    #[test]
+    fn too_many_ranges_are_ignored() {
+        let many = (0..=MAX_RANGES).map(|i| format!("{}-{}", i, i)).collect::<Vec<_>>().join(",");
+        assert_eq!(ranges(&format!("bytes={}", many), 100).unwrap(), None);
+    }
+
+    #[test]
+    fn if_range_etag() {
+        assert_eq!(with_if_range("\"abc\""), Some(vec![(0, 10)]));
+        assert_eq!(with_if_range("\"other\""), None);
+        // If-Range 使用强比较，弱 ETag 不成立
+        assert_eq!(with_if_range("W/\"abc\""), None);
+    }
+
+    #[test]
+    fn if_range_date() {
+        let modified = resource(100).modified;
+        assert_eq!(with_if_range(&conditional::http_date(&modified)), Some(vec![(0, 10)]));
+        let earlier = modified - chrono::Duration::seconds(1);
+        assert_eq!(with_if_range(&conditional::http_date(&earlier)), None);
+        assert_eq!(with_if_range("not a date"), None);
+    }
+
+    #[test]
+    fn content_range_header() {
+        assert_eq!(content_range(&(0..10), 100), "bytes 0-9/100");
+        assert_eq!(content_range(&(99..100), 100), "bytes 99-99/100");
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{percent_decode_str, percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// 路径段中除 RFC 3986 的 unreserved 字符之外全部编码
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// 拆分 URI 引用，返回 `(scheme, authority, path)`，相对引用的前两项为 `None`。
+///
+/// 查询串和片段会被丢弃。
+pub(crate) fn split_uri(uri: &str) -> (Option<&str>, Option<&str>, &str) {
+    let uri = uri.split(['?', '#']).next().unwrap_or_default();
+    match uri.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = match rest.find('/') {
+                Some(index) => rest.split_at(index),
+                None => (rest, "/"),
+            };
+            (Some(scheme), Some(authority), path)
+        }
+        None => (None, None, uri),
+    }
+}
+
+/// 比较 URI 中的 authority 与请求的 `Host` 头，忽略大小写和协议的默认端口
+pub(crate) fn same_authority(scheme: &str, authority: &str, host: &str) -> bool {
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "http" => ":80",
+        "https" => ":443",
+        _ => "",
+    };
+    let strip = |value: &str| -> String {
+        let value = value.to_ascii_lowercase();
+        match value.strip_suffix(default_port) {
+            Some(stripped) if !default_port.is_empty() => stripped.to_string(),
+            _ => value,
+        }
+    };
+    strip(authority) == strip(host)
+}
+
+/// 将 URL 或绝对路径映射为后端路径，不在 `prefix` 挂载点下时返回 `None`
+pub(crate) fn href_to_path(href: &str, prefix: &str) -> Option<PathBuf> {
+    let (_, _, path) = split_uri(href);
+    decode_path(strip_mount_prefix(path, prefix)?)
+}
+
+/// 去掉路径开头的挂载点，挂载点只能作为完整的前导路径段匹配
+pub(crate) fn strip_mount_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(prefix.trim_end_matches('/'))?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(rest)
+}
+
+/// 对路径逐段进行百分号解码并规范化：忽略空段和 `.`，`..` 回退一级。
+///
+/// 越过根目录、解码后不是合法 UTF-8 或段内含有 `/`、NUL 时返回 `None`。
+pub(crate) fn decode_path(path: &str) -> Option<PathBuf> {
+    let mut segments: Vec<String> = Vec::new();
+    for segment in path.split('/') {
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        match segment.as_ref() {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            name if name.contains(['/', '\\', '\0']) => return None,
+            name => segments.push(name.to_string()),
+        }
+    }
+    Some(segments.iter().collect())
+}
+
+/// 将后端路径编码为带挂载点前缀的绝对路径 href，集合以 `/` 结尾
+pub(crate) fn path_to_href(path: &Path, is_dir: bool, prefix: &str) -> String {
+    let mut href = prefix.trim_end_matches('/').to_string();
+    for segment in path.iter() {
+        href.push('/');
+        href.extend(percent_encode(segment.as_encoded_bytes(), SEGMENT));
+    }
+    if is_dir || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_uri("http://example.com:8080/dav/a?x=1#top"),
+            (Some("http"), Some("example.com:8080"), "/dav/a")
+        );
+        assert_eq!(split_uri("https://example.com"), (Some("https"), Some("example.com"), "/"));
+        assert_eq!(split_uri("/dav/a?x=1"), (None, None, "/dav/a"));
+    }
+
+    #[test]
+    fn default_ports() {
+        assert!(same_authority("http", "example.com:80", "example.com"));
+        assert!(same_authority("http", "example.com", "example.com:80"));
+        assert!(same_authority("HTTPS", "Example.COM:443", "example.com"));
+        assert!(!same_authority("http", "example.com:443", "example.com"));
+        assert!(!same_authority("https", "example.com:80", "example.com"));
+        assert!(!same_authority("http", "example.com:8080", "example.com"));
+        assert!(!same_authority("ftp", "example.com:21", "example.com"));
+        assert!(!same_authority("http", "example.org", "example.com"));
+    }
+
+    #[test]
+    fn mount_prefix_matches_whole_segments() {
+        assert_eq!(strip_mount_prefix("/dav", "/dav"), Some(""));
+        assert_eq!(strip_mount_prefix("/dav/a", "/dav"), Some("/a"));
+        assert_eq!(strip_mount_prefix("/dav/a", "/dav/"), Some("/a"));
+        assert_eq!(strip_mount_prefix("/davx/a", "/dav"), None);
+        assert_eq!(strip_mount_prefix("/other/dav/a", "/dav"), None);
+        assert_eq!(strip_mount_prefix("/a", ""), Some("/a"));
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_path("/a/b%20c/"), Some(PathBuf::from("a/b c")));
+        assert_eq!(decode_path("/a/./b//c"), Some(PathBuf::from("a/b/c")));
+        assert_eq!(decode_path("/a/b/../c"), Some(PathBuf::from("a/c")));
+        assert_eq!(decode_path("/a/.."), Some(PathBuf::new()));
+        assert_eq!(decode_path("/%E4%B8%AD%E6%96%87"), Some(PathBuf::from("中文")));
+    }
+
+    #[test]
+    fn decode_rejects_escapes() {
+        assert_eq!(decode_path("/.."), None);
+        assert_eq!(decode_path("/a/../../b"), None);
+        assert_eq!(decode_path("/a/%2E%2E/%2e%2e/b"), None);
+        assert_eq!(decode_path("/a%2Fb"), None);
+        assert_eq!(decode_path("/a%2fb"), None);
+        assert_eq!(decode_path("/a%5Cb"), None);
+        assert_eq!(decode_path("/a%00b"), None);
+        assert_eq!(decode_path("/%FF"), None);
+    }
+
+    #[test]
+    fn href_to_backend_path() {
+        assert_eq!(href_to_path("http://example.com/dav/a/b", "/dav"), Some(PathBuf::from("a/b")));
+        assert_eq!(href_to_path("/dav/a%20b?x", "/dav"), Some(PathBuf::from("a b")));
+        assert_eq!(href_to_path("/dav/", "/dav"), Some(PathBuf::new()));
+        assert_eq!(href_to_path("/davx/a", "/dav"), None);
+        assert_eq!(href_to_path("/dav/../etc", "/dav"), None);
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(path_to_href(Path::new("a b/c#d"), false, "/dav"), "/dav/a%20b/c%23d");
+        assert_eq!(path_to_href(Path::new("dir"), true, "/dav/"), "/dav/dir/");
+        assert_eq!(path_to_href(Path::new(""), true, "/dav"), "/dav/");
+        assert_eq!(path_to_href(Path::new(""), false, ""), "/");
+        assert_eq!(path_to_href(Path::new("中"), false, ""), "/%E4%B8%AD");
+    }
+}
@@ -1,4 +1,7 @@
+pub mod auth;
 pub mod backend;
+pub mod config;
 pub mod error;
 pub mod handler;
-pub mod xml; 
\ No newline at end of file
+pub mod lock;
+pub mod xml;
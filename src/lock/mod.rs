@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::backend::Depth;
+use crate::error::WebDavError;
+use crate::xml::LockInfo;
+
+/// 客户端未发送 `Timeout` 头时使用的超时时间
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// 锁超时时间的上限，`Timeout: Infinite` 也会被限制到该值
+pub const MAX_LOCK_TIMEOUT: Duration = Duration::from_secs(7 * 24 * 3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockScope {
+    Exclusive,
+    Shared,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveLock {
+    pub token: String,
+    pub root: PathBuf,
+    /// 锁根是否为集合，集合的 href 以 `/` 结尾
+    pub is_dir: bool,
+    pub scope: LockScope,
+    pub depth: Depth,
+    /// `D:owner` 的内容，已序列化为 XML
+    pub owner: Option<String>,
+    /// 创建锁的用户名，未启用认证时为 `None`
+    pub principal: Option<String>,
+    pub timeout: Duration,
+    expires_at: Instant,
+}
+
+impl ActiveLock {
+    /// 判断该锁是否覆盖给定路径
+    pub fn covers(&self, path: &Path) -> bool {
+        self.root == path || (self.depth == Depth::Infinity && path.starts_with(&self.root))
+    }
+
+    /// 锁是否由 `principal` 创建，只有创建者可以使用锁令牌（RFC 4918 第 6.4 节）
+    pub fn held_by(&self, principal: Option<&str>) -> bool {
+        self.principal.as_deref() == principal
+    }
+
+    /// 剩余的有效时间
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at <= Instant::now()
+    }
+}
+
+/// 进程内的锁管理器，锁不会被持久化
+#[derive(Debug, Default)]
+pub struct LockManager {
+    locks: RwLock<HashMap<String, ActiveLock>>,
+}
+
+impl LockManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在 `path` 上为 `principal` 创建一个新的写锁，`is_dir` 表示 `path` 是否为集合
+    pub fn lock(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        principal: Option<&str>,
+        info: LockInfo,
+        depth: Depth,
+        timeout: Duration,
+    ) -> Result<ActiveLock, WebDavError> {
+        let LockInfo { scope, owner } = info;
+        let mut locks = self.locks.write().unwrap();
+        locks.retain(|_, lock| !lock.is_expired());
+
+        let conflict = locks.values().any(|lock| {
+            let overlaps = lock.covers(path)
+                || (depth == Depth::Infinity && lock.root.starts_with(path));
+            overlaps && (lock.scope == LockScope::Exclusive || scope == LockScope::Exclusive)
+        });
+        if conflict {
+            return Err(WebDavError::LockConflict);
+        }
+
+        let timeout = timeout.min(MAX_LOCK_TIMEOUT);
+        let lock = ActiveLock {
+            token: format!("opaquelocktoken:{}", Uuid::new_v4()),
+            root: path.to_path_buf(),
+            is_dir,
+            scope,
+            depth,
+            owner,
+            principal: principal.map(str::to_string),
+            timeout,
+            expires_at: Instant::now() + timeout,
+        };
+        locks.insert(lock.token.clone(), lock.clone());
+        Ok(lock)
+    }
+
+    /// 刷新已有的锁，令牌必须覆盖 `path` 且由 `principal` 创建
+    pub fn refresh(
+        &self,
+        path: &Path,
+        token: &str,
+        principal: Option<&str>,
+        timeout: Duration,
+    ) -> Result<ActiveLock, WebDavError> {
+        let mut locks = self.locks.write().unwrap();
+        locks.retain(|_, lock| !lock.is_expired());
+
+        match locks.get_mut(token) {
+            Some(lock) if lock.covers(path) && !lock.held_by(principal) => {
+                Err(WebDavError::PermissionDenied(path.to_path_buf()))
+            }
+            Some(lock) if lock.covers(path) => {
+                lock.timeout = timeout.min(MAX_LOCK_TIMEOUT);
+                lock.expires_at = Instant::now() + lock.timeout;
+                Ok(lock.clone())
+            }
+            _ => Err(WebDavError::PreconditionFailed(format!(
+                "Lock token {} does not apply to {}",
+                token,
+                path.display()
+            ))),
+        }
+    }
+
+    /// 释放锁，令牌必须覆盖 `path` 且由 `principal` 创建
+    pub fn unlock(&self, path: &Path, token: &str, principal: Option<&str>) -> Result<(), WebDavError> {
+        let mut locks = self.locks.write().unwrap();
+        locks.retain(|_, lock| !lock.is_expired());
+
+        match locks.get(token) {
+            Some(lock) if lock.covers(path) && !lock.held_by(principal) => {
+                Err(WebDavError::PermissionDenied(path.to_path_buf()))
+            }
+            Some(lock) if lock.covers(path) => {
+                locks.remove(token);
+                Ok(())
+            }
+            _ => Err(WebDavError::Conflict(format!(
+                "Lock token {} does not match {}",
+                token,
+                path.display()
+            ))),
+        }
+    }
+
+    /// 返回所有覆盖 `path` 的锁
+    pub fn discover(&self, path: &Path) -> Vec<ActiveLock> {
+        let locks = self.locks.read().unwrap();
+        locks
+            .values()
+            .filter(|lock| !lock.is_expired() && lock.covers(path))
+            .cloned()
+            .collect()
+    }
+
+    /// 返回阻止对 `path` 写操作的锁的根路径及其是否为集合，为空表示允许写入。
+    ///
+    /// `deep` 为 true 时，`path` 之下的锁同样需要提交令牌（用于集合的 DELETE/MOVE 等）。
+    /// 同一根路径上的多个共享锁，提交其中任意一个令牌即可。
+    /// 其他用户创建的锁的令牌视为没有提交。
+    pub fn blocking_roots(
+        &self,
+        path: &Path,
+        tokens: &[String],
+        principal: Option<&str>,
+        deep: bool,
+    ) -> Vec<(PathBuf, bool)> {
+        let locks = self.locks.read().unwrap();
+        let mut satisfied: HashMap<(&Path, bool), bool> = HashMap::new();
+        for lock in locks.values() {
+            if lock.is_expired() || !(lock.covers(path) || (deep && lock.root.starts_with(path))) {
+                continue;
+            }
+            let submitted = tokens.contains(&lock.token) && lock.held_by(principal);
+            *satisfied.entry((lock.root.as_path(), lock.is_dir)).or_insert(false) |= submitted;
+        }
+        satisfied
+            .into_iter()
+            .filter(|(_, ok)| !ok)
+            .map(|((root, is_dir), _)| (root.to_path_buf(), is_dir))
+            .collect()
+    }
+
+    /// 移除以 `path` 或其子路径为根的锁，在资源被删除或移走后调用
+    pub fn remove_tree(&self, path: &Path) {
+        let mut locks = self.locks.write().unwrap();
+        locks.retain(|_, lock| !lock.is_expired() && !lock.root.starts_with(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(scope: LockScope) -> LockInfo {
+        LockInfo { scope, owner: None }
+    }
+
+    fn lock(
+        manager: &LockManager,
+        path: &str,
+        scope: LockScope,
+        depth: Depth,
+    ) -> Result<ActiveLock, WebDavError> {
+        manager.lock(Path::new(path), false, Some("alice"), info(scope), depth, DEFAULT_LOCK_TIMEOUT)
+    }
+
+    #[test]
+    fn shared_locks_coexist_but_exclusive_conflicts() {
+        let manager = LockManager::new();
+        lock(&manager, "a.txt", LockScope::Shared, Depth::Zero).unwrap();
+        lock(&manager, "a.txt", LockScope::Shared, Depth::Zero).unwrap();
+        assert!(matches!(
+            lock(&manager, "a.txt", LockScope::Exclusive, Depth::Zero),
+            Err(WebDavError::LockConflict)
+        ));
+
+        lock(&manager, "b.txt", LockScope::Exclusive, Depth::Zero).unwrap();
+        assert!(lock(&manager, "b.txt", LockScope::Shared, Depth::Zero).is_err());
+        assert_eq!(manager.discover(Path::new("a.txt")).len(), 2);
+    }
+
+    #[test]
+    fn depth_infinity_covers_descendants() {
+        let manager = LockManager::new();
+        lock(&manager, "dir", LockScope::Exclusive, Depth::Infinity).unwrap();
+        assert!(lock(&manager, "dir/sub/file.txt", LockScope::Exclusive, Depth::Zero).is_err());
+        // 只是前缀相同的兄弟路径不受影响
+        lock(&manager, "directory", LockScope::Exclusive, Depth::Zero).unwrap();
+
+        let manager = LockManager::new();
+        lock(&manager, "dir", LockScope::Exclusive, Depth::Zero).unwrap();
+        lock(&manager, "dir/file.txt", LockScope::Exclusive, Depth::Zero).unwrap();
+    }
+
+    #[test]
+    fn depth_infinity_conflicts_with_existing_descendant_lock() {
+        let manager = LockManager::new();
+        lock(&manager, "dir/file.txt", LockScope::Exclusive, Depth::Zero).unwrap();
+        assert!(lock(&manager, "dir", LockScope::Exclusive, Depth::Infinity).is_err());
+        lock(&manager, "dir", LockScope::Exclusive, Depth::Zero).unwrap();
+    }
+
+    #[test]
+    fn expired_locks_are_dropped() {
+        let manager = LockManager::new();
+        let path = Path::new("a.txt");
+        let expiring = manager
+            .lock(path, false, None, info(LockScope::Exclusive), Depth::Zero, Duration::from_millis(10))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(manager.discover(path).is_empty());
+        assert!(manager.blocking_roots(path, &[], None, false).is_empty());
+        assert!(manager.refresh(path, &expiring.token, None, DEFAULT_LOCK_TIMEOUT).is_err());
+        lock(&manager, "a.txt", LockScope::Exclusive, Depth::Zero).unwrap();
+    }
+
+    #[test]
+    fn refresh_extends_the_lock() {
+        let manager = LockManager::new();
+        let path = Path::new("dir/file.txt");
+        let active = manager
+            .lock(path, false, Some("alice"), info(LockScope::Exclusive), Depth::Zero, Duration::from_secs(1))
+            .unwrap();
+
+        let refreshed = manager.refresh(path, &active.token, Some("alice"), Duration::from_secs(60)).unwrap();
+        assert_eq!(refreshed.timeout, Duration::from_secs(60));
+        assert!(refreshed.remaining() > Duration::from_secs(1));
+
+        // 超过上限的超时时间被截断
+        let refreshed = manager.refresh(path, &active.token, Some("alice"), Duration::MAX).unwrap();
+        assert_eq!(refreshed.timeout, MAX_LOCK_TIMEOUT);
+
+        assert!(matches!(
+            manager.refresh(Path::new("other.txt"), &active.token, Some("alice"), DEFAULT_LOCK_TIMEOUT),
+            Err(WebDavError::PreconditionFailed(_))
+        ));
+        assert!(matches!(
+            manager.refresh(path, "opaquelocktoken:unknown", Some("alice"), DEFAULT_LOCK_TIMEOUT),
+            Err(WebDavError::PreconditionFailed(_))
+        ));
+        assert!(matches!(
+            manager.refresh(path, &active.token, Some("bob"), DEFAULT_LOCK_TIMEOUT),
+            Err(WebDavError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn unlock_requires_matching_path_and_principal() {
+        let manager = LockManager::new();
+        let active = lock(&manager, "dir", LockScope::Exclusive, Depth::Infinity).unwrap();
+
+        assert!(manager.unlock(Path::new("other"), &active.token, Some("alice")).is_err());
+        assert!(manager.unlock(Path::new("dir"), &active.token, Some("bob")).is_err());
+        // 深度锁可以在其覆盖的成员上解锁
+        manager.unlock(Path::new("dir/file.txt"), &active.token, Some("alice")).unwrap();
+        assert!(manager.discover(Path::new("dir")).is_empty());
+    }
+
+    #[test]
+    fn blocking_roots_require_submitted_tokens() {
+        let manager = LockManager::new();
+        let active = lock(&manager, "dir/file.txt", LockScope::Exclusive, Depth::Zero).unwrap();
+        let tokens = [active.token.clone()];
+        let file = Path::new("dir/file.txt");
+
+        assert_eq!(manager.blocking_roots(file, &[], Some("alice"), false), [(file.to_path_buf(), false)]);
+        assert!(manager.blocking_roots(file, &tokens, Some("alice"), false).is_empty());
+        assert_eq!(manager.blocking_roots(file, &tokens, Some("bob"), false).len(), 1);
+
+        // 锁定成员只在 `deep` 时阻止对集合的操作
+        let dir = Path::new("dir");
+        assert!(manager.blocking_roots(dir, &[], Some("alice"), false).is_empty());
+        assert_eq!(manager.blocking_roots(dir, &[], Some("alice"), true).len(), 1);
+        assert!(manager.blocking_roots(dir, &tokens, Some("alice"), true).is_empty());
+
+        manager.remove_tree(dir);
+        assert!(manager.blocking_roots(file, &[], Some("alice"), false).is_empty());
+    }
+
+    #[test]
+    fn any_shared_token_satisfies_the_root() {
+        let manager = LockManager::new();
+        let first = lock(&manager, "a.txt", LockScope::Shared, Depth::Zero).unwrap();
+        lock(&manager, "a.txt", LockScope::Shared, Depth::Zero).unwrap();
+        assert!(manager.blocking_roots(Path::new("a.txt"), &[first.token], Some("alice"), false).is_empty());
+    }
+}
@@ -1,95 +1,263 @@
 use axum::{
-    routing::any,
     Router,
     extract::Path,
     body::Body,
+    middleware,
     http::{Request, Method, Response, StatusCode},
 };
-use std::net::SocketAddr;
+use clap::Parser;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rustls::TlsAcceptor;
 use tower_http::trace::{TraceLayer, DefaultMakeSpan, DefaultOnResponse};
-use tracing::{Level, info, error};
+use tracing::{Level, info, warn, error};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use webdav_rs::{
+    auth::{self, Authentication, BasicAuthenticator, BearerAuthenticator, DigestAuthenticator, Principal},
     backend::fs::FileSystemBackend,
+    config::Config,
     handler::WebDavHandler,
     error::WebDavError,
 };
 
+/// 命令行参数，优先于配置文件中的设置
+#[derive(Parser, Debug)]
+#[command(version, about = "A WebDAV server implementation in Rust")]
+struct Cli {
+    /// TOML 配置文件
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// 主挂载点的存储目录
+    #[arg(short, long)]
+    root: Option<PathBuf>,
+
+    /// 监听的 IP 地址
+    #[arg(short, long)]
+    bind: Option<String>,
+
+    /// 监听的端口
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// 主挂载点的 URL 前缀，`/` 表示挂载在根路径
+    #[arg(long)]
+    prefix: Option<String>,
+
+    /// 以只读模式提供主挂载点
+    #[arg(long)]
+    read_only: bool,
+
+    /// 日志过滤规则，语法同 RUST_LOG
+    #[arg(long)]
+    log: Option<String>,
+
+    /// 检查配置是否有效后退出
+    #[arg(long)]
+    check_config: bool,
+}
+
+impl Cli {
+    fn apply(&self, config: &mut Config) {
+        if let Some(root) = &self.root {
+            config.server.root = Some(root.clone());
+        }
+        if let Some(bind) = &self.bind {
+            config.server.bind = bind.clone();
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+        if let Some(prefix) = &self.prefix {
+            config.server.prefix = prefix.clone();
+        }
+        if self.read_only {
+            config.server.read_only = true;
+        }
+    }
+}
+
+type Handler = WebDavHandler<FileSystemBackend>;
+
 #[tokio::main]
-async fn main() {
-    // 初始化日志
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut config = match &cli.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Config::default(),
+    };
+    cli.apply(&mut config);
+
+    if let Err(e) = config.validate() {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    if cli.check_config {
+        println!("Configuration OK");
+        return ExitCode::SUCCESS;
+    }
+
+    // 初始化日志：命令行 > RUST_LOG > 配置文件
+    let filter = cli
+        .log
+        .clone()
+        .or_else(|| std::env::var("RUST_LOG").ok())
+        .unwrap_or_else(|| config.server.log.clone());
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
+        .with(tracing_subscriber::EnvFilter::new(filter))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // 创建后端
-    let backend = FileSystemBackend::new("./storage");
-    let handler = WebDavHandler::new(backend);
+    // 为每个挂载点创建后端和处理器，较长的前缀优先匹配
+    let mut mounts = config.mounts();
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.prefix.trim_matches('/').len()));
+    let mut handlers = Vec::with_capacity(mounts.len());
+    for mount in mounts {
+        if let Err(e) = tokio::fs::create_dir_all(&mount.root).await {
+            error!("Failed to create {}: {}", mount.root.display(), e);
+            return ExitCode::FAILURE;
+        }
+        let backend = config.storage.backend(&mount.root);
+        match backend.cleanup_temp_files().await {
+            Ok(0) => {}
+            Ok(count) => info!("Removed {} orphaned temporary files", count),
+            Err(e) => error!("Failed to clean up temporary files: {}", e),
+        }
+        info!(
+            prefix = %mount.prefix,
+            root = %mount.root.display(),
+            read_only = mount.read_only,
+            home_directories = mount.home_directories,
+            "Mounted storage"
+        );
+        handlers.push(
+            WebDavHandler::new(backend)
+                .with_mount_prefix(&mount.prefix)
+                .with_read_only(mount.read_only)
+                .with_home_directories(mount.home_directories, mount.admin_sees_all)
+                .with_infinite_depth(config.limits.allow_infinite_depth)
+                .with_max_upload_size(config.limits.max_upload_size),
+        );
+    }
+    let handlers = Arc::new(handlers);
+
+    // 配置了用户或令牌时启用认证，质询按 Digest、Basic、Bearer 的顺序
+    let loaded = config.user_database().and_then(|users| {
+        Ok((users, config.digest_credentials()?, config.token_database()?, config.jwt_verifier()?))
+    });
+    let (users, digest, tokens, jwt) = match loaded {
+        Ok((users, digest, tokens, jwt)) => (Arc::new(users), digest, tokens, jwt),
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let realm = &config.auth.realm;
+    let mut authentication = Authentication::new();
+    if let Some(digest) = digest {
+        info!(realm = %realm, "Digest authentication enabled");
+        authentication = authentication.with_authenticator(DigestAuthenticator::new(
+            Arc::new(digest),
+            users.clone(),
+            realm,
+            Duration::from_secs(config.auth.nonce_lifetime),
+        ));
+    }
+    if !users.is_empty() {
+        info!(users = users.len(), realm = %realm, "Basic authentication enabled");
+        authentication = authentication.with_authenticator(BasicAuthenticator::new(users, realm));
+    }
+    if tokens.is_some() || jwt.is_some() {
+        info!(jwt = jwt.is_some(), tokens = tokens.is_some(), "Bearer authentication enabled");
+        authentication = authentication.with_authenticator(BearerAuthenticator::new(
+            tokens.unwrap_or_default(),
+            jwt,
+            realm,
+        ));
+    }
+    if authentication.is_empty() && config.auth_enabled() {
+        error!("Authentication is configured but no users or tokens were loaded");
+        return ExitCode::FAILURE;
+    }
+    if authentication.is_empty() {
+        warn!("No users configured, allowing anonymous access");
+    }
 
-    // 创建路由
+    // 创建路由，所有路径都由处理器按挂载点分发
     let app = Router::new()
-        .route(
-            "/*path",
-            any(move |method: Method, path: Path<String>, req: Request<Body>| {
-                let handler = handler.clone();
-            
-                async move {
-                    let path_str = path.0.clone();
-                    info!(
-                        method = %method,
-                        path = %path_str,
-                        headers = ?req.headers(),
-                        "Handling WebDAV request"
-                    );
-                    
-
-                
-                let result = if path_str.starts_with("dav/") {
-                    // 去掉dav
-                    let origin_path = Path(path_str.replace("dav/", ""));
-                    match method.as_str() {
-                        "PROPFIND" => handler.handle_propfind(origin_path, req).await,
-                        "GET" => handler.handle_get(origin_path).await,
-                        "PUT" => handler.handle_put(origin_path, req).await,
-                        "MKCOL" => handler.handle_mkcol(origin_path).await,
-                        "DELETE" => handler.handle_delete(origin_path).await,
-                        "COPY" => handler.handle_copy(origin_path, req).await,
-                        "MOVE" => handler.handle_move(origin_path, req).await,
-                        "OPTIONS" => Ok(Response::builder()
-                            .status(StatusCode::OK)
-                            .header("Allow", "OPTIONS, GET, HEAD, POST, PUT, DELETE, PROPFIND, MKCOL, COPY, MOVE")
-                            .header("DAV", "1, 2")
-                            .body(Body::empty())
-                            .unwrap()),
-                        _ => Err(WebDavError::InvalidInput("Method not allowed".to_string())),
+        .fallback(move |method: Method, req: Request<Body>| {
+            let handlers = handlers.clone();
+
+            async move {
+                let path_str = req.uri().path().to_string();
+                let user = req.extensions().get::<Principal>().map(|principal| principal.name.clone());
+                info!(
+                    method = %method,
+                    path = %path_str,
+                    user = ?user,
+                    headers = ?req.headers(),
+                    "Handling WebDAV request"
+                );
+
+                let result = match find_handler(&handlers, &path_str) {
+                    Some((handler, origin_path)) => {
+                        let origin_path = Path(origin_path.to_string());
+                        match method.as_str() {
+                            "PROPFIND" => handler.handle_propfind(origin_path, req).await,
+                            "PROPPATCH" => handler.handle_proppatch(origin_path, req).await,
+                            "GET" => handler.handle_get(origin_path, req).await,
+                            "HEAD" => handler.handle_head(origin_path, req).await,
+                            "PUT" => handler.handle_put(origin_path, req).await,
+                            "MKCOL" => handler.handle_mkcol(origin_path, req).await,
+                            "DELETE" => handler.handle_delete(origin_path, req).await,
+                            "COPY" => handler.handle_copy(origin_path, req).await,
+                            "MOVE" => handler.handle_move(origin_path, req).await,
+                            "LOCK" => handler.handle_lock(origin_path, req).await,
+                            "UNLOCK" => handler.handle_unlock(origin_path, req).await,
+                            "OPTIONS" => Ok(Response::builder()
+                                .status(StatusCode::OK)
+                                .header("Allow", "OPTIONS, GET, HEAD, POST, PUT, DELETE, PROPFIND, PROPPATCH, MKCOL, COPY, MOVE, LOCK, UNLOCK")
+                                .header("DAV", "1, 2")
+                                .body(Body::empty())
+                                .unwrap()),
+                            _ => Err(WebDavError::InvalidInput("Method not allowed".to_string())),
+                        }
                     }
-                } else {
-                    Err(WebDavError::InvalidInput("Path must start with dav/".to_string()))
+                    None => Err(WebDavError::NotFound(path_str.clone().into())),
                 };
-                    
-
-                    match &result {
-                        Ok(response) => info!(
-                            method = %method,
-                            path = %path_str,
-                            status = ?response.status(),
-                            "Request completed successfully"
-                        ),
-                        Err(e) => error!(
-                            method = %method,
-                            path = %path_str,
-                            error = %e,
-                            "Request failed"
-                        ),
-                    }
 
-                    result
+                match &result {
+                    Ok(response) => info!(
+                        method = %method,
+                        path = %path_str,
+                        status = ?response.status(),
+                        "Request completed successfully"
+                    ),
+                    Err(e) => error!(
+                        method = %method,
+                        path = %path_str,
+                        error = %e,
+                        "Request failed"
+                    ),
                 }
-            }),
-        )
+
+                result
+            }
+        })
+        .layer(middleware::from_fn_with_state(Arc::new(authentication), auth::require_auth))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
@@ -97,8 +265,77 @@ async fn main() {
         );
 
     // 启动服务器
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    info!("WebDAV server listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let addr = match config.addr() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to listen on {}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match &config.tls {
+        Some(tls) => {
+            let acceptor = match tls.server_config() {
+                Ok(server_config) => TlsAcceptor::from(server_config),
+                Err(e) => {
+                    error!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            info!("WebDAV server listening on https://{}", addr);
+            serve_tls(listener, acceptor, app).await;
+        }
+        None => {
+            info!("WebDAV server listening on http://{}", addr);
+            if let Err(e) = axum::serve(listener, app).await {
+                error!("Server error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// 选择挂载点匹配请求路径的处理器，返回处理器和去掉前缀后的路径
+fn find_handler<'a, 'p>(handlers: &'a [Handler], path: &'p str) -> Option<(&'a Handler, &'p str)> {
+    handlers
+        .iter()
+        .find_map(|handler| handler.strip_mount_prefix(path).map(|rest| (handler, rest)))
+}
+
+/// 在 TLS 连接上提供服务，握手失败只影响对应的连接
+async fn serve_tls(listener: tokio::net::TcpListener, acceptor: TlsAcceptor, app: Router) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!(peer = %peer, "TLS handshake failed: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                error!(peer = %peer, "Connection error: {}", e);
+            }
+        });
+    }
 }
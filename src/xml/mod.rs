@@ -1,11 +1,84 @@
+use quick_xml::events::attributes::Attribute;
 use quick_xml::events::{BytesStart, BytesEnd, BytesText, Event};
+use quick_xml::name::{Namespace, QName, ResolveResult};
+use quick_xml::reader::NsReader;
 use quick_xml::Writer;
 use std::io::Cursor;
 use std::sync::Arc;
 
-use crate::backend::ResourceMetadata;
+use http::StatusCode;
 
-pub fn create_multistatus_response(resources: &[ResourceMetadata]) -> Result<String, quick_xml::Error> {
+use crate::backend::{DeadProperty, Depth, PropertyUpdate};
+use crate::error::WebDavError;
+use crate::handler::uri;
+use crate::lock::{ActiveLock, LockScope};
+
+const DAV_NS: &[u8] = b"DAV:";
+
+/// 带命名空间的属性名
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+
+    /// DAV: 命名空间下的属性
+    pub fn dav(name: &str) -> Self {
+        Self::new("DAV:", name)
+    }
+
+    pub fn is_dav(&self) -> bool {
+        self.namespace.as_bytes() == DAV_NS
+    }
+}
+
+/// 属性值
+#[derive(Debug, Clone)]
+pub enum PropValue {
+    Empty,
+    Text(String),
+    /// 已序列化的 XML 片段，按原样写出
+    Xml(String),
+}
+
+#[derive(Debug)]
+pub struct Propstat {
+    pub status: StatusCode,
+    pub props: Vec<(PropName, PropValue)>,
+}
+
+/// multistatus 中一个资源的响应
+#[derive(Debug)]
+pub struct PropResponse {
+    pub href: String,
+    pub propstats: Vec<Propstat>,
+}
+
+/// multistatus 中只带状态码的响应，用于报告集合操作中失败的成员
+#[derive(Debug)]
+pub struct StatusResponse {
+    pub href: String,
+    pub status: StatusCode,
+}
+
+/// PROPFIND 请求体
+#[derive(Debug)]
+pub enum PropfindRequest {
+    /// `D:allprop`，附带 `D:include` 中额外请求的属性
+    AllProp(Vec<PropName>),
+    PropName,
+    Prop(Vec<PropName>),
+}
+
+pub fn create_multistatus_response(responses: &[PropResponse]) -> Result<String, quick_xml::Error> {
     let mut writer = Writer::new(Cursor::new(Vec::new()));
     
     // Write XML declaration
@@ -16,101 +89,441 @@ pub fn create_multistatus_response(resources: &[ResourceMetadata]) -> Result<Str
     multistatus.push_attribute(("xmlns:D", "DAV:"));
     writer.write_event(Event::Start(multistatus))?;
 
-    for resource in resources {
-        write_response(&mut writer, resource)?;
+    for response in responses {
+        write_response(&mut writer, response)?;
     }
 
     // End D:multistatus
     writer.write_event(Event::End(BytesEnd::new("D:multistatus")))?;
 
+    into_string(writer)
+}
+
+/// 创建 COPY/MOVE/DELETE 部分失败时的 multistatus 响应体，每个成员一个 `D:status`
+pub fn create_status_multistatus_response(responses: &[StatusResponse]) -> Result<String, quick_xml::Error> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    let mut multistatus = BytesStart::new("D:multistatus");
+    multistatus.push_attribute(("xmlns:D", "DAV:"));
+    writer.write_event(Event::Start(multistatus))?;
+
+    for response in responses {
+        writer.write_event(Event::Start(BytesStart::new("D:response")))?;
+        write_text_element(&mut writer, "D:href", &response.href)?;
+        write_text_element(&mut writer, "D:status", &status_line(response.status))?;
+        writer.write_event(Event::End(BytesEnd::new("D:response")))?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("D:multistatus")))?;
+
+    into_string(writer)
+}
+
+fn into_string(writer: Writer<Cursor<Vec<u8>>>) -> Result<String, quick_xml::Error> {
     let result = writer.into_inner().into_inner();
-    String::from_utf8(result).map_err(|e| quick_xml::Error::Io(Arc::new(std::io::Error::new(std::io::ErrorKind::Other, e))))
+    String::from_utf8(result).map_err(|e| quick_xml::Error::Io(Arc::new(std::io::Error::other(e))))
 }
 
-fn write_response(writer: &mut Writer<Cursor<Vec<u8>>>, resource: &ResourceMetadata) -> Result<(), quick_xml::Error> {
+fn write_text_element(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, text: &str) -> Result<(), quick_xml::Error> {
+    writer.write_event(Event::Start(BytesStart::new(name)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+fn status_line(status: StatusCode) -> String {
+    format!("HTTP/1.1 {} {}", status.as_u16(), status.canonical_reason().unwrap_or(""))
+}
+
+fn write_response(writer: &mut Writer<Cursor<Vec<u8>>>, response: &PropResponse) -> Result<(), quick_xml::Error> {
     // Start D:response
     writer.write_event(Event::Start(BytesStart::new("D:response")))?;
 
     // Write D:href
-    writer.write_event(Event::Start(BytesStart::new("D:href")))?;
-    writer.write_event(Event::Text(BytesText::new(&resource.path.to_string_lossy())))?;
-    writer.write_event(Event::End(BytesEnd::new("D:href")))?;
+    write_text_element(writer, "D:href", &response.href)?;
 
-    // Write D:propstat
-    writer.write_event(Event::Start(BytesStart::new("D:propstat")))?;
+    for propstat in &response.propstats {
+        // Write D:propstat
+        writer.write_event(Event::Start(BytesStart::new("D:propstat")))?;
+        writer.write_event(Event::Start(BytesStart::new("D:prop")))?;
+        for (name, value) in &propstat.props {
+            write_property(writer, name, value)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("D:prop")))?;
 
-    // Write D:prop
-    writer.write_event(Event::Start(BytesStart::new("D:prop")))?;
+        // Write D:status
+        write_text_element(writer, "D:status", &status_line(propstat.status))?;
 
-    // Write resource type
-    writer.write_event(Event::Start(BytesStart::new("D:resourcetype")))?;
-    if resource.is_dir {
-        writer.write_event(Event::Empty(BytesStart::new("D:collection")))?;
+        // End D:propstat
+        writer.write_event(Event::End(BytesEnd::new("D:propstat")))?;
     }
-    writer.write_event(Event::End(BytesEnd::new("D:resourcetype")))?;
 
-    // Write getcontentlength
-    if !resource.is_dir {
-        writer.write_event(Event::Start(BytesStart::new("D:getcontentlength")))?;
-        writer.write_event(Event::Text(BytesText::new(&resource.len.to_string())))?;
-        writer.write_event(Event::End(BytesEnd::new("D:getcontentlength")))?;
-    }
+    // End D:response
+    writer.write_event(Event::End(BytesEnd::new("D:response")))?;
 
-    // Write getlastmodified
-    writer.write_event(Event::Start(BytesStart::new("D:getlastmodified")))?;
-    writer.write_event(Event::Text(BytesText::new(&resource.modified.to_rfc2822())))?;
-    writer.write_event(Event::End(BytesEnd::new("D:getlastmodified")))?;
+    Ok(())
+}
 
-    // Write getetag
-    writer.write_event(Event::Start(BytesStart::new("D:getetag")))?;
-    writer.write_event(Event::Text(BytesText::new(&resource.etag)))?;
-    writer.write_event(Event::End(BytesEnd::new("D:getetag")))?;
+/// 写出一个属性元素，非 DAV: 命名空间的属性在元素上声明默认命名空间
+fn write_property(writer: &mut Writer<Cursor<Vec<u8>>>, name: &PropName, value: &PropValue) -> Result<(), quick_xml::Error> {
+    let (tag, start) = if name.is_dav() {
+        let tag = format!("D:{}", name.name);
+        (tag.clone(), BytesStart::new(tag))
+    } else {
+        let mut start = BytesStart::new(name.name.clone());
+        start.push_attribute(("xmlns", name.namespace.as_str()));
+        (name.name.clone(), start)
+    };
 
-    // End D:prop
-    writer.write_event(Event::End(BytesEnd::new("D:prop")))?;
+    match value {
+        PropValue::Empty => writer.write_event(Event::Empty(start))?,
+        PropValue::Text(text) => {
+            writer.write_event(Event::Start(start))?;
+            writer.write_event(Event::Text(BytesText::new(text)))?;
+            writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        }
+        PropValue::Xml(xml) => {
+            writer.write_event(Event::Start(start))?;
+            writer.write_event(Event::Text(BytesText::from_escaped(xml.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        }
+    }
+    Ok(())
+}
 
-    // Write D:status
-    writer.write_event(Event::Start(BytesStart::new("D:status")))?;
-    writer.write_event(Event::Text(BytesText::new("HTTP/1.1 200 OK")))?;
-    writer.write_event(Event::End(BytesEnd::new("D:status")))?;
+/// 解析 PROPFIND 请求体，空请求体等同于 `D:allprop`
+pub fn parse_propfind_request(xml: &[u8]) -> Result<PropfindRequest, WebDavError> {
+    if xml.iter().all(u8::is_ascii_whitespace) {
+        return Ok(PropfindRequest::AllProp(Vec::new()));
+    }
 
-    // End D:propstat
-    writer.write_event(Event::End(BytesEnd::new("D:propstat")))?;
+    let mut reader = NsReader::from_reader(xml);
+    reader.trim_text(true);
+    let mut level = 0usize;
+    let mut mode: Option<&'static str> = None;
+    let mut container: Option<&'static str> = None;
+    let mut names = Vec::new();
 
-    // End D:response
-    writer.write_event(Event::End(BytesEnd::new("D:response")))?;
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(xml_error)?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_start = matches!(event, Event::Start(_));
+                let is_dav = matches!(ns, ResolveResult::Bound(Namespace(DAV_NS)));
+                let local = e.local_name();
+                match level {
+                    0 if !(is_dav && local.as_ref() == b"propfind") => {
+                        return Err(WebDavError::XmlError("expected DAV:propfind".to_string()));
+                    }
+                    1 if is_dav => {
+                        container = match local.as_ref() {
+                            b"prop" => Some("prop"),
+                            b"allprop" => Some("allprop"),
+                            b"propname" => Some("propname"),
+                            b"include" => Some("include"),
+                            _ => None,
+                        };
+                        if container != Some("include") && container.is_some() {
+                            mode = container;
+                        }
+                    }
+                    2 if matches!(container, Some("prop") | Some("include")) => {
+                        let namespace = match ns {
+                            ResolveResult::Bound(Namespace(ns)) => String::from_utf8_lossy(ns).into_owned(),
+                            _ => String::new(),
+                        };
+                        names.push(PropName::new(namespace, String::from_utf8_lossy(local.as_ref())));
+                        if is_start {
+                            // 属性元素的内容与请求无关
+                            let end = e.to_end().into_owned();
+                            reader.read_to_end(end.name()).map_err(xml_error)?;
+                            continue;
+                        }
+                    }
+                    _ => {}
+                }
+                if is_start {
+                    level += 1;
+                }
+            }
+            Event::End(_) => {
+                level = level.saturating_sub(1);
+                if level == 1 {
+                    container = None;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
 
-    Ok(())
+    match mode {
+        Some("prop") => Ok(PropfindRequest::Prop(names)),
+        Some("allprop") => Ok(PropfindRequest::AllProp(names)),
+        Some("propname") => Ok(PropfindRequest::PropName),
+        _ => Err(WebDavError::XmlError("propfind must contain prop, allprop or propname".to_string())),
+    }
 }
 
-pub fn parse_propfind_request(xml: &[u8]) -> Result<Vec<String>, quick_xml::Error> {
-    let mut reader = quick_xml::Reader::from_reader(xml);
-    let mut buf = Vec::new();
-    let mut properties = Vec::new();
-    let mut in_prop = false;
+/// 解析 PROPPATCH 请求体（`D:propertyupdate`），按文档顺序返回所有修改
+pub fn parse_proppatch_request(xml: &[u8]) -> Result<Vec<PropertyUpdate>, WebDavError> {
+    let mut reader = NsReader::from_reader(xml);
+    reader.trim_text(true);
+    let mut level = 0usize;
+    let mut setting: Option<bool> = None;
+    let mut updates = Vec::new();
+    let mut found_root = false;
 
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                match e.name().as_ref() {
-                    b"prop" => in_prop = true,
-                    name if in_prop => {
-                        properties.push(String::from_utf8_lossy(name).into_owned());
+        let (ns, event) = reader.read_resolved_event().map_err(xml_error)?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_start = matches!(event, Event::Start(_));
+                let is_dav = matches!(ns, ResolveResult::Bound(Namespace(DAV_NS)));
+                let local = e.local_name();
+                match level {
+                    0 if is_dav && local.as_ref() == b"propertyupdate" => found_root = true,
+                    0 => return Err(WebDavError::XmlError("expected DAV:propertyupdate".to_string())),
+                    1 if is_dav => {
+                        setting = match local.as_ref() {
+                            b"set" => Some(true),
+                            b"remove" => Some(false),
+                            _ => None,
+                        };
                     }
+                    3 if setting.is_some() => {
+                        let namespace = match ns {
+                            ResolveResult::Bound(Namespace(ns)) => String::from_utf8_lossy(ns).into_owned(),
+                            _ => String::new(),
+                        };
+                        let name = String::from_utf8_lossy(local.as_ref()).into_owned();
+                        let value = if is_start && setting == Some(true) {
+                            read_inner_xml(&mut reader)?
+                        } else {
+                            if is_start {
+                                let end = e.to_end().into_owned();
+                                reader.read_to_end(end.name()).map_err(xml_error)?;
+                            }
+                            String::new()
+                        };
+                        updates.push(if setting == Some(true) {
+                            PropertyUpdate::Set(DeadProperty { namespace, name, value })
+                        } else {
+                            PropertyUpdate::Remove { namespace, name }
+                        });
+                        continue;
+                    }
+                    _ => {}
+                }
+                if is_start {
+                    level += 1;
+                }
+            }
+            Event::End(_) => {
+                level = level.saturating_sub(1);
+                if level == 1 {
+                    setting = None;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !found_root {
+        return Err(WebDavError::XmlError("expected DAV:propertyupdate".to_string()));
+    }
+    Ok(updates)
+}
+
+/// LOCK 请求体（`D:lockinfo`）中客户端提供的信息
+#[derive(Debug)]
+pub struct LockInfo {
+    pub scope: LockScope,
+    pub owner: Option<String>,
+}
+
+pub fn parse_lockinfo(xml: &[u8]) -> Result<LockInfo, WebDavError> {
+    let mut reader = NsReader::from_reader(xml);
+    reader.trim_text(true);
+    let mut scope = None;
+    let mut owner = None;
+
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(xml_error)?;
+        let is_dav = matches!(ns, ResolveResult::Bound(Namespace(DAV_NS)));
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) if is_dav => {
+                let is_start = matches!(event, Event::Start(_));
+                match e.local_name().as_ref() {
+                    b"exclusive" => scope = Some(LockScope::Exclusive),
+                    b"shared" => scope = Some(LockScope::Shared),
+                    b"owner" if is_start => owner = Some(read_inner_xml(&mut reader)?),
+                    b"owner" => owner = Some(String::new()),
                     _ => {}
                 }
             }
-            Ok(Event::End(ref e)) => {
-                if e.name().as_ref() == b"prop" {
-                    in_prop = false;
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let scope = scope.ok_or_else(|| WebDavError::XmlError("lockinfo is missing lockscope".to_string()))?;
+    Ok(LockInfo { scope, owner })
+}
+
+/// 读取当前元素的全部子内容并重新序列化。
+///
+/// 每个子元素都会带上自己的 `xmlns` 声明，这样结果可以脱离原文档的前缀声明单独保存和输出。
+fn read_inner_xml(reader: &mut NsReader<&[u8]>) -> Result<String, WebDavError> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    let mut depth = 0usize;
+
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(xml_error)?;
+        match event {
+            Event::Start(e) => {
+                depth += 1;
+                writer.write_event(Event::Start(qualify_element(&ns, &e))).map_err(xml_error)?;
+            }
+            Event::Empty(e) => {
+                writer.write_event(Event::Empty(qualify_element(&ns, &e))).map_err(xml_error)?;
+            }
+            Event::End(e) => {
+                if depth == 0 {
+                    break;
                 }
+                depth -= 1;
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                writer.write_event(Event::End(BytesEnd::new(name))).map_err(xml_error)?;
             }
-            Ok(Event::Eof) => break,
-            Err(e) => return Err(e),
+            Event::Text(e) => writer.write_event(Event::Text(e)).map_err(xml_error)?,
+            Event::CData(e) => writer.write_event(Event::CData(e)).map_err(xml_error)?,
+            Event::Eof => return Err(WebDavError::XmlError("unexpected end of document".to_string())),
             _ => {}
         }
-        buf.clear();
     }
 
-    Ok(properties)
-} 
\ No newline at end of file
+    into_string(writer).map_err(xml_error)
+}
+
+fn qualify_element(ns: &ResolveResult, e: &BytesStart) -> BytesStart<'static> {
+    let local = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+    let namespace = match ns {
+        ResolveResult::Bound(Namespace(ns)) => String::from_utf8_lossy(ns).into_owned(),
+        _ => String::new(),
+    };
+
+    let mut element = BytesStart::new(local);
+    element.push_attribute(("xmlns", namespace.as_str()));
+    for attr in e.attributes().flatten() {
+        let key = attr.key;
+        if key.as_namespace_binding().is_some() {
+            continue;
+        }
+        let keep_prefix = key.prefix().is_some_and(|p| p.as_ref() == b"xml");
+        let key = if keep_prefix { key } else { QName(key.local_name().into_inner()) };
+        element.push_attribute(Attribute { key, value: attr.value });
+    }
+    element.into_owned()
+}
+
+fn xml_error(e: quick_xml::Error) -> WebDavError {
+    WebDavError::XmlError(e.to_string())
+}
+
+/// 创建 `D:error` 响应体，`condition` 为 DAV: 命名空间下的条件元素名，`hrefs` 写为其子元素
+pub fn create_error_response(condition: &str, hrefs: &[String]) -> Result<String, quick_xml::Error> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    let mut error = BytesStart::new("D:error");
+    error.push_attribute(("xmlns:D", "DAV:"));
+    writer.write_event(Event::Start(error))?;
+    let tag = format!("D:{}", condition);
+    if hrefs.is_empty() {
+        writer.write_event(Event::Empty(BytesStart::new(tag.as_str())))?;
+    } else {
+        writer.write_event(Event::Start(BytesStart::new(tag.as_str())))?;
+        for href in hrefs {
+            write_text_element(&mut writer, "D:href", href)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("D:error")))?;
+
+    into_string(writer)
+}
+
+/// 创建 LOCK 请求的响应体
+pub fn create_lockdiscovery_response(locks: &[ActiveLock], mount_prefix: &str) -> Result<String, quick_xml::Error> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::Decl(quick_xml::events::BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    let mut prop = BytesStart::new("D:prop");
+    prop.push_attribute(("xmlns:D", "DAV:"));
+    writer.write_event(Event::Start(prop))?;
+    write_lockdiscovery(&mut writer, locks, mount_prefix)?;
+    writer.write_event(Event::End(BytesEnd::new("D:prop")))?;
+
+    into_string(writer)
+}
+
+/// 生成 `D:lockdiscovery` 属性的内容（若干 `D:activelock`），用于 PROPFIND
+pub fn create_activelocks(locks: &[ActiveLock], mount_prefix: &str) -> Result<String, quick_xml::Error> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    write_activelocks(&mut writer, locks, mount_prefix)?;
+    into_string(writer)
+}
+
+fn write_lockdiscovery(writer: &mut Writer<Cursor<Vec<u8>>>, locks: &[ActiveLock], mount_prefix: &str) -> Result<(), quick_xml::Error> {
+    writer.write_event(Event::Start(BytesStart::new("D:lockdiscovery")))?;
+    write_activelocks(writer, locks, mount_prefix)?;
+    writer.write_event(Event::End(BytesEnd::new("D:lockdiscovery")))?;
+    Ok(())
+}
+
+fn write_activelocks(writer: &mut Writer<Cursor<Vec<u8>>>, locks: &[ActiveLock], mount_prefix: &str) -> Result<(), quick_xml::Error> {
+    for lock in locks {
+        writer.write_event(Event::Start(BytesStart::new("D:activelock")))?;
+
+        writer.write_event(Event::Start(BytesStart::new("D:locktype")))?;
+        writer.write_event(Event::Empty(BytesStart::new("D:write")))?;
+        writer.write_event(Event::End(BytesEnd::new("D:locktype")))?;
+
+        writer.write_event(Event::Start(BytesStart::new("D:lockscope")))?;
+        let scope = match lock.scope {
+            LockScope::Exclusive => "D:exclusive",
+            LockScope::Shared => "D:shared",
+        };
+        writer.write_event(Event::Empty(BytesStart::new(scope)))?;
+        writer.write_event(Event::End(BytesEnd::new("D:lockscope")))?;
+
+        let depth = match lock.depth {
+            Depth::Zero => "0",
+            Depth::One => "1",
+            Depth::Infinity => "infinity",
+        };
+        write_text_element(writer, "D:depth", depth)?;
+
+        if let Some(owner) = &lock.owner {
+            writer.write_event(Event::Start(BytesStart::new("D:owner")))?;
+            writer.write_event(Event::Text(BytesText::from_escaped(owner.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::new("D:owner")))?;
+        }
+
+        write_text_element(writer, "D:timeout", &format!("Second-{}", lock.remaining().as_millis().div_ceil(1000)))?;
+
+        writer.write_event(Event::Start(BytesStart::new("D:locktoken")))?;
+        write_text_element(writer, "D:href", &lock.token)?;
+        writer.write_event(Event::End(BytesEnd::new("D:locktoken")))?;
+
+        writer.write_event(Event::Start(BytesStart::new("D:lockroot")))?;
+        write_text_element(writer, "D:href", &uri::path_to_href(&lock.root, lock.is_dir, mount_prefix))?;
+        writer.write_event(Event::End(BytesEnd::new("D:lockroot")))?;
+
+        writer.write_event(Event::End(BytesEnd::new("D:activelock")))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! 家目录模式的访问控制：用户只能访问自己的家目录，管理员可以按配置访问所有家目录
+
+use axum::body::Body;
+use axum::extract::Path as UrlPath;
+use axum::http::{Request, StatusCode};
+use tempfile::TempDir;
+use webdav_rs::auth::{Access, Principal};
+use webdav_rs::backend::fs::FileSystemBackend;
+use webdav_rs::handler::WebDavHandler;
+
+/// 存储根目录下有 alice 和 bob 两个家目录
+fn setup(admin_sees_all: bool) -> (TempDir, WebDavHandler<FileSystemBackend>) {
+    let dir = tempfile::tempdir().unwrap();
+    for home in ["alice", "bob"] {
+        std::fs::create_dir(dir.path().join(home)).unwrap();
+        std::fs::write(dir.path().join(home).join("notes.txt"), home).unwrap();
+    }
+    let handler = WebDavHandler::new(FileSystemBackend::new(dir.path())).with_home_directories(true, admin_sees_all);
+    (dir, handler)
+}
+
+fn user(name: &str, admin: bool) -> Principal {
+    Principal {
+        name: name.to_string(),
+        admin,
+        access: Access::default(),
+    }
+}
+
+fn request(method: &str, principal: &Principal, headers: &[(&str, &str)], body: &'static str) -> Request<Body> {
+    let mut builder = Request::builder().method(method).header("Host", "localhost");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let mut request = builder.body(Body::from(body)).unwrap();
+    request.extensions_mut().insert(principal.clone());
+    request
+}
+
+fn url(path: &str) -> UrlPath<String> {
+    UrlPath(path.to_string())
+}
+
+/// Depth: 1 的 PROPFIND 返回的所有 href
+async fn list_root(handler: &WebDavHandler<FileSystemBackend>, principal: &Principal) -> Vec<String> {
+    let response = handler
+        .handle_propfind(url("/"), request("PROPFIND", principal, &[("Depth", "1")], ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let mut hrefs: Vec<_> = body
+        .split("<D:href>")
+        .skip(1)
+        .map(|rest| rest.split("</D:href>").next().unwrap().to_string())
+        .collect();
+    hrefs.sort();
+    hrefs
+}
+
+#[tokio::test]
+async fn confined_user_sees_only_own_home() {
+    let (_dir, handler) = setup(true);
+    assert_eq!(list_root(&handler, &user("alice", false)).await, ["/dav/", "/dav/alice/"]);
+
+    let get = request("GET", &user("alice", false), &[], "");
+    let error = handler.handle_get(url("bob/notes.txt"), get).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn confined_user_cannot_write_into_other_homes() {
+    let (dir, handler) = setup(true);
+    let alice = user("alice", false);
+
+    let put = request("PUT", &alice, &[], "alice");
+    let error = handler.handle_put(url("bob/new.txt"), put).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let destination = [("Destination", "http://localhost/dav/bob/copied.txt")];
+    let copy = request("COPY", &alice, &destination, "");
+    let error = handler.handle_copy(url("alice/notes.txt"), copy).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let destination = [("Destination", "http://localhost/dav/bob/moved.txt")];
+    let moved = request("MOVE", &alice, &destination, "");
+    let error = handler.handle_move(url("alice/notes.txt"), moved).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    // 也不能从其他用户的家目录中复制出来
+    let destination = [("Destination", "http://localhost/dav/alice/stolen.txt")];
+    let copy = request("COPY", &alice, &destination, "");
+    let error = handler.handle_copy(url("bob/notes.txt"), copy).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    assert!(!dir.path().join("bob/new.txt").exists());
+    assert!(!dir.path().join("bob/copied.txt").exists());
+    assert!(!dir.path().join("bob/moved.txt").exists());
+    assert!(!dir.path().join("alice/stolen.txt").exists());
+    assert!(dir.path().join("alice/notes.txt").exists());
+}
+
+#[tokio::test]
+async fn confined_user_cannot_remove_own_home() {
+    let (dir, handler) = setup(true);
+    let alice = user("alice", false);
+
+    let delete = request("DELETE", &alice, &[], "");
+    let error = handler.handle_delete(url("alice"), delete).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let destination = [("Destination", "http://localhost/dav/alice/renamed")];
+    let moved = request("MOVE", &alice, &destination, "");
+    let error = handler.handle_move(url("alice"), moved).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    // 家目录中的内容可以正常修改
+    let put = request("PUT", &alice, &[], "updated");
+    assert!(handler.handle_put(url("alice/notes.txt"), put).await.is_ok());
+    assert!(dir.path().join("alice").is_dir());
+}
+
+#[tokio::test]
+async fn admin_sees_every_home_when_allowed() {
+    let (_dir, handler) = setup(true);
+    assert_eq!(
+        list_root(&handler, &user("root", true)).await,
+        ["/dav/", "/dav/alice/", "/dav/bob/", "/dav/root/"]
+    );
+
+    let get = request("GET", &user("root", true), &[], "");
+    assert!(handler.handle_get(url("bob/notes.txt"), get).await.is_ok());
+}
+
+#[tokio::test]
+async fn admin_is_confined_without_admin_sees_all() {
+    let (_dir, handler) = setup(false);
+    assert_eq!(list_root(&handler, &user("root", true)).await, ["/dav/", "/dav/root/"]);
+}
+
+/// 根集合的 ETag
+async fn root_etag(handler: &WebDavHandler<FileSystemBackend>, principal: &Principal) -> String {
+    let response = handler
+        .handle_propfind(url("/"), request("PROPFIND", principal, &[("Depth", "0")], ""))
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    body.split("<D:getetag>").nth(1).unwrap().split("</D:getetag>").next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn root_etag_does_not_reveal_other_users_activity() {
+    let (_dir, handler) = setup(true);
+    let (alice, bob) = (user("alice", false), user("bob", false));
+    let before = root_etag(&handler, &alice).await;
+
+    let put = request("PUT", &bob, &[], "bob");
+    handler.handle_put(url("bob/new.txt"), put).await.unwrap();
+    let mkcol = request("MKCOL", &bob, &[], "");
+    handler.handle_mkcol(url("bob/dir"), mkcol).await.unwrap();
+    assert_eq!(root_etag(&handler, &alice).await, before);
+
+    // 自己家目录的变化仍然反映在根集合上
+    let put = request("PUT", &alice, &[], "alice");
+    handler.handle_put(url("alice/new.txt"), put).await.unwrap();
+    assert_ne!(root_etag(&handler, &alice).await, before);
+}
@@ -0,0 +1,109 @@
+//! 锁与认证用户的关联：锁令牌只能由创建锁的用户使用
+
+use axum::body::Body;
+use axum::extract::Path as UrlPath;
+use axum::http::{Request, StatusCode};
+use tempfile::TempDir;
+use webdav_rs::auth::{Access, Principal};
+use webdav_rs::backend::fs::FileSystemBackend;
+use webdav_rs::handler::WebDavHandler;
+
+const LOCKINFO: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:lockinfo xmlns:D="DAV:">
+  <D:lockscope><D:exclusive/></D:lockscope>
+  <D:locktype><D:write/></D:locktype>
+</D:lockinfo>"#;
+
+fn setup() -> (TempDir, WebDavHandler<FileSystemBackend>) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file.txt"), "original").unwrap();
+    let handler = WebDavHandler::new(FileSystemBackend::new(dir.path()));
+    (dir, handler)
+}
+
+fn user(name: &str) -> Principal {
+    Principal {
+        name: name.to_string(),
+        admin: false,
+        access: Access::default(),
+    }
+}
+
+/// 以 `principal` 的身份构造请求，认证中间件同样将用户放入 extensions
+fn request(method: &str, principal: &Principal, headers: &[(&str, &str)], body: &'static str) -> Request<Body> {
+    let mut builder = Request::builder().method(method);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let mut request = builder.body(Body::from(body)).unwrap();
+    request.extensions_mut().insert(principal.clone());
+    request
+}
+
+fn url(path: &str) -> UrlPath<String> {
+    UrlPath(path.to_string())
+}
+
+#[tokio::test]
+async fn lock_token_is_bound_to_its_creator() {
+    let (dir, handler) = setup();
+    let (alice, bob) = (user("alice"), user("bob"));
+
+    let response = handler
+        .handle_lock(url("file.txt"), request("LOCK", &alice, &[], LOCKINFO))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response.headers()["Lock-Token"].to_str().unwrap().to_string();
+    let if_header = format!("({})", token);
+
+    // bob 从 lockdiscovery 中得到了 alice 的令牌，也不能用它写入、刷新或解锁
+    let put = request("PUT", &bob, &[("If", &if_header)], "bob");
+    let error = handler.handle_put(url("file.txt"), put).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::LOCKED);
+
+    let refresh = request("LOCK", &bob, &[("If", &if_header)], "");
+    let error = handler.handle_lock(url("file.txt"), refresh).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::PRECONDITION_FAILED);
+
+    let unlock = request("UNLOCK", &bob, &[("Lock-Token", &token)], "");
+    let error = handler.handle_unlock(url("file.txt"), unlock).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(std::fs::read_to_string(dir.path().join("file.txt")).unwrap(), "original");
+
+    // 创建者可以正常使用
+    let refresh = request("LOCK", &alice, &[("If", &if_header)], "");
+    let response = handler.handle_lock(url("file.txt"), refresh).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let put = request("PUT", &alice, &[("If", &if_header)], "alice");
+    let response = handler.handle_put(url("file.txt"), put).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(std::fs::read_to_string(dir.path().join("file.txt")).unwrap(), "alice");
+
+    let unlock = request("UNLOCK", &alice, &[("Lock-Token", &token)], "");
+    let response = handler.handle_unlock(url("file.txt"), unlock).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // 锁释放后 bob 可以写入
+    let put = request("PUT", &bob, &[], "bob");
+    assert!(handler.handle_put(url("file.txt"), put).await.is_ok());
+}
+
+#[tokio::test]
+async fn conflicting_lock_does_not_create_resource() {
+    let (dir, handler) = setup();
+    std::fs::create_dir(dir.path().join("dir")).unwrap();
+    let alice = user("alice");
+
+    let lock = request("LOCK", &alice, &[("Depth", "infinity")], LOCKINFO);
+    let response = handler.handle_lock(url("dir"), lock).await.unwrap();
+    let token = response.headers()["Lock-Token"].to_str().unwrap().to_string();
+
+    // 提交了父集合的令牌可以在其中创建成员，但新的排他锁与父集合的深度锁冲突
+    let if_header = format!("({})", token);
+    let lock = request("LOCK", &alice, &[("If", &if_header)], LOCKINFO);
+    let error = handler.handle_lock(url("dir/new.txt"), lock).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::LOCKED);
+    assert!(!dir.path().join("dir/new.txt").exists());
+}
@@ -0,0 +1,279 @@
+//! FileSystemBackend 路径解析的回归测试：恶意路径不能访问存储根目录之外的内容
+#![cfg(unix)]
+
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+use axum::body::Body;
+use axum::extract::Path as UrlPath;
+use axum::http::{Request, StatusCode};
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use tempfile::TempDir;
+use webdav_rs::backend::fs::{FileSystemBackend, SymlinkPolicy, PROPS_FILE};
+use webdav_rs::backend::{Backend, ByteStream, Depth};
+use webdav_rs::error::WebDavError;
+use webdav_rs::handler::WebDavHandler;
+
+/// 创建 `<tmp>/root` 作为存储根目录，`<tmp>/outside/secret.txt` 位于根目录之外
+fn setup() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(root.join("docs")).unwrap();
+    std::fs::write(root.join("docs/readme.txt"), "inside").unwrap();
+    std::fs::create_dir_all(dir.path().join("outside")).unwrap();
+    std::fs::write(dir.path().join("outside/secret.txt"), "secret").unwrap();
+    (dir, root)
+}
+
+fn body(content: &'static str) -> ByteStream {
+    Box::pin(stream::once(async move { Ok(Bytes::from(content)) }))
+}
+
+async fn read_all(backend: &FileSystemBackend, path: &str) -> Result<String, WebDavError> {
+    let mut stream = backend.read_file(Path::new(path), None).await?;
+    let mut content = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+    Ok(String::from_utf8(content).unwrap())
+}
+
+const MALICIOUS_PATHS: &[&str] = &[
+    "../outside/secret.txt",
+    "/../outside/secret.txt",
+    "docs/../../outside/secret.txt",
+    "docs/../../../etc/passwd",
+    "./../outside/secret.txt",
+    "..",
+    "docs/..",
+    "docs/readme.txt\0.jpg",
+    PROPS_FILE,
+    "docs/.webdav-props.json",
+    ".webdav-tmp-1234",
+    "docs/.webdav-tmp-abcd",
+    // 大小写不敏感或去掉结尾 `.`、空格的文件系统上与内部文件同名
+    ".WEBDAV-PROPS.JSON",
+    "docs/.Webdav-Props.Json",
+    ".webdav-props.json.",
+    "docs/.webdav-props.json ",
+    ".WEBDAV-TMP-1234",
+    // Windows 设备名和 NTFS 备用数据流
+    "CON",
+    "docs/nul.txt",
+    "com1",
+    "LPT9.log",
+    "docs/readme.txt::$DATA",
+    "docs/readme.txt:stream",
+    "docs/readme.txt.",
+];
+
+#[tokio::test]
+async fn rejects_malicious_paths_for_reads() {
+    let (_dir, root) = setup();
+    let backend = FileSystemBackend::new(&root);
+    for path in MALICIOUS_PATHS {
+        assert!(
+            backend.get_resource(Path::new(path)).await.is_err(),
+            "get_resource accepted {:?}",
+            path
+        );
+        assert!(read_all(&backend, path).await.is_err(), "read_file accepted {:?}", path);
+        assert!(
+            backend.get_properties(Path::new(path)).await.is_err(),
+            "get_properties accepted {:?}",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn rejects_malicious_paths_for_writes() {
+    let (dir, root) = setup();
+    let backend = FileSystemBackend::new(&root);
+    for path in MALICIOUS_PATHS {
+        let path = Path::new(path);
+        assert!(backend.write_file(path, body("pwned")).await.is_err(), "write_file accepted {:?}", path);
+        assert!(backend.create_dir(path).await.is_err(), "create_dir accepted {:?}", path);
+        assert!(backend.delete(path).await.is_err(), "delete accepted {:?}", path);
+    }
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("outside/secret.txt")).unwrap(),
+        "secret"
+    );
+    assert!(root.join("docs/readme.txt").exists());
+}
+
+#[tokio::test]
+async fn rejects_malicious_copy_and_move() {
+    let (dir, root) = setup();
+    let backend = FileSystemBackend::new(&root);
+    let inside = Path::new("docs/readme.txt");
+    for path in MALICIOUS_PATHS {
+        let path = Path::new(path);
+        assert!(backend.copy(inside, path, Depth::Infinity).await.is_err(), "copy to {:?}", path);
+        assert!(backend.copy(path, Path::new("stolen.txt"), Depth::Infinity).await.is_err(), "copy from {:?}", path);
+        assert!(backend.move_resource(inside, path).await.is_err(), "move to {:?}", path);
+        assert!(backend.move_resource(path, Path::new("stolen.txt")).await.is_err(), "move from {:?}", path);
+    }
+    assert!(root.join("docs/readme.txt").exists());
+    assert!(!root.join("stolen.txt").exists());
+    assert!(!dir.path().join("outside/readme.txt").exists());
+}
+
+#[tokio::test]
+async fn absolute_paths_stay_inside_root() {
+    let (_dir, root) = setup();
+    let backend = FileSystemBackend::new(&root);
+    assert!(matches!(
+        backend.get_resource(Path::new("/etc/passwd")).await,
+        Err(WebDavError::NotFound(_))
+    ));
+    assert_eq!(read_all(&backend, "/docs/readme.txt").await.unwrap(), "inside");
+}
+
+#[tokio::test]
+async fn internal_files_are_hidden_from_listings() {
+    let (_dir, root) = setup();
+    std::fs::write(root.join(PROPS_FILE), "{}").unwrap();
+    std::fs::write(root.join(".webdav-tmp-stale"), "partial").unwrap();
+    let backend = FileSystemBackend::new(&root);
+    let info = backend.get_resource(Path::new("")).await.unwrap();
+    let names: Vec<_> = info
+        .children
+        .unwrap()
+        .into_iter()
+        .map(|child| child.path)
+        .collect();
+    assert_eq!(names, vec![PathBuf::from("docs")]);
+}
+
+/// 在根目录中创建指向外部目录和内部目录的符号链接
+fn setup_symlinks() -> (TempDir, PathBuf) {
+    let (dir, root) = setup();
+    symlink(dir.path().join("outside"), root.join("escape")).unwrap();
+    symlink(root.join("docs"), root.join("alias")).unwrap();
+    symlink(dir.path().join("missing"), root.join("dangling")).unwrap();
+    (dir, root)
+}
+
+#[tokio::test]
+async fn symlinks_denied() {
+    let (_dir, root) = setup_symlinks();
+    let backend = FileSystemBackend::new(&root).with_symlink_policy(SymlinkPolicy::Deny);
+    for path in ["escape/secret.txt", "alias/readme.txt", "dangling/file"] {
+        assert!(matches!(
+            read_all(&backend, path).await,
+            Err(WebDavError::PermissionDenied(_))
+        ));
+        assert!(backend.write_file(Path::new(path), body("x")).await.is_err());
+    }
+    let info = backend.get_resource(Path::new("")).await.unwrap();
+    assert_eq!(info.children.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn symlinks_followed_within_root() {
+    let (dir, root) = setup_symlinks();
+    let backend = FileSystemBackend::new(&root).with_symlink_policy(SymlinkPolicy::FollowWithinRoot);
+    assert_eq!(read_all(&backend, "alias/readme.txt").await.unwrap(), "inside");
+    assert!(matches!(
+        read_all(&backend, "escape/secret.txt").await,
+        Err(WebDavError::PermissionDenied(_))
+    ));
+    assert!(backend.write_file(Path::new("escape/new.txt"), body("x")).await.is_err());
+    assert!(backend.write_file(Path::new("dangling/new.txt"), body("x")).await.is_err());
+    assert!(backend
+        .copy(Path::new("escape"), Path::new("copied"), Depth::Infinity)
+        .await
+        .is_err());
+    assert!(backend
+        .move_resource(Path::new("escape/secret.txt"), Path::new("moved.txt"))
+        .await
+        .is_err());
+    assert!(!dir.path().join("outside/new.txt").exists());
+    assert!(dir.path().join("outside/secret.txt").exists());
+
+    let info = backend.get_resource(Path::new("")).await.unwrap();
+    let mut names: Vec<_> = info.children.unwrap().into_iter().map(|child| child.path).collect();
+    names.sort();
+    assert_eq!(names, vec![PathBuf::from("alias"), PathBuf::from("docs")]);
+}
+
+#[tokio::test]
+async fn copy_skips_escaping_symlinks_inside_collections() {
+    let (dir, root) = setup_symlinks();
+    std::fs::create_dir(root.join("tree")).unwrap();
+    std::fs::write(root.join("tree/file.txt"), "data").unwrap();
+    symlink(dir.path().join("outside/secret.txt"), root.join("tree/leak.txt")).unwrap();
+    let backend = FileSystemBackend::new(&root);
+
+    match backend.copy(Path::new("tree"), Path::new("copy"), Depth::Infinity).await {
+        Err(WebDavError::Multistatus(failures)) => {
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].path, PathBuf::from("copy/leak.txt"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(root.join("copy/file.txt").exists());
+    assert!(!root.join("copy/leak.txt").exists());
+}
+
+#[tokio::test]
+async fn symlinks_followed_everywhere() {
+    let (_dir, root) = setup_symlinks();
+    let backend = FileSystemBackend::new(&root).with_symlink_policy(SymlinkPolicy::FollowAll);
+    assert_eq!(read_all(&backend, "escape/secret.txt").await.unwrap(), "secret");
+    assert_eq!(read_all(&backend, "alias/readme.txt").await.unwrap(), "inside");
+    // `..` 即使在允许跟随所有链接时也会被拒绝
+    assert!(read_all(&backend, "escape/../../outside/secret.txt").await.is_err());
+}
+
+/// 在根目录中创建两个指向根目录自身的符号链接，展开时路径数会指数增长
+fn setup_loops() -> (TempDir, PathBuf) {
+    let (dir, root) = setup();
+    symlink(&root, root.join("loop")).unwrap();
+    symlink(&root, root.join("docs/up")).unwrap();
+    (dir, root)
+}
+
+#[tokio::test]
+async fn propfind_infinity_stops_at_symlink_loops() {
+    let (_dir, root) = setup_loops();
+    let handler = WebDavHandler::new(FileSystemBackend::new(&root));
+    let request = Request::builder()
+        .method("PROPFIND")
+        .header("Depth", "infinity")
+        .body(Body::empty())
+        .unwrap();
+    let response = handler
+        .handle_propfind(UrlPath("/".to_string()), request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    // 根目录、docs、docs/readme.txt、loop、docs/up，链接本身列出但不再展开
+    assert_eq!(body.matches("<D:response>").count(), 5, "{}", body);
+}
+
+#[tokio::test]
+async fn copy_stops_at_symlink_loops() {
+    let (_dir, root) = setup_loops();
+    let backend = FileSystemBackend::new(&root);
+
+    match backend.copy(Path::new("docs"), Path::new("copy"), Depth::Infinity).await {
+        Err(WebDavError::Multistatus(failures)) => {
+            assert!(!failures.is_empty());
+            assert!(failures
+                .iter()
+                .all(|failure| failure.status == StatusCode::LOOP_DETECTED));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(root.join("copy/readme.txt").exists());
+    // docs/up 指向根目录，其中的 docs 和新建的 copy 都已访问过，不会再被复制
+    assert!(!root.join("copy/up/docs").exists());
+    assert!(!root.join("copy/up/copy").exists());
+    assert!(!root.join("copy/up/loop").exists());
+}
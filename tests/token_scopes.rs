@@ -0,0 +1,133 @@
+//! 令牌的权限范围和路径前缀：只读令牌不能修改资源，受限令牌不能访问前缀之外的资源
+
+use std::path::Path;
+
+use axum::body::Body;
+use axum::extract::Path as UrlPath;
+use axum::http::{Request, StatusCode};
+use tempfile::TempDir;
+use webdav_rs::auth::{Access, Principal, Scope};
+use webdav_rs::backend::fs::FileSystemBackend;
+use webdav_rs::backend::Backend;
+use webdav_rs::handler::WebDavHandler;
+
+const LOCKINFO: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:lockinfo xmlns:D="DAV:">
+  <D:lockscope><D:exclusive/></D:lockscope>
+  <D:locktype><D:write/></D:locktype>
+</D:lockinfo>"#;
+
+fn setup() -> (TempDir, WebDavHandler<FileSystemBackend>) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("up")).unwrap();
+    std::fs::create_dir(dir.path().join("upload")).unwrap();
+    std::fs::write(dir.path().join("upload/file.txt"), "upload").unwrap();
+    std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+    let handler = WebDavHandler::new(FileSystemBackend::new(dir.path()));
+    (dir, handler)
+}
+
+fn token(path_prefix: Option<&str>, scope: Scope) -> Principal {
+    Principal {
+        name: "ci".to_string(),
+        admin: false,
+        access: Access::restricted(path_prefix, scope).unwrap(),
+    }
+}
+
+fn request(method: &str, principal: &Principal, headers: &[(&str, &str)], body: &'static str) -> Request<Body> {
+    let mut builder = Request::builder().method(method).header("Host", "localhost");
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let mut request = builder.body(Body::from(body)).unwrap();
+    request.extensions_mut().insert(principal.clone());
+    request
+}
+
+fn url(path: &str) -> UrlPath<String> {
+    UrlPath(path.to_string())
+}
+
+#[tokio::test]
+async fn read_scope_cannot_modify() {
+    let (dir, handler) = setup();
+    let reader = token(None, Scope::Read);
+
+    let get = request("GET", &reader, &[], "");
+    assert!(handler.handle_get(url("secret.txt"), get).await.is_ok());
+
+    let put = request("PUT", &reader, &[], "changed");
+    let error = handler.handle_put(url("secret.txt"), put).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let lock = request("LOCK", &reader, &[], LOCKINFO);
+    let error = handler.handle_lock(url("secret.txt"), lock).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let delete = request("DELETE", &reader, &[], "");
+    let error = handler.handle_delete(url("secret.txt"), delete).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    assert_eq!(std::fs::read_to_string(dir.path().join("secret.txt")).unwrap(), "secret");
+}
+
+#[tokio::test]
+async fn path_prefix_matches_whole_segments() {
+    let (dir, handler) = setup();
+    let writer = token(Some("/dav/up"), Scope::ReadWrite);
+
+    let put = request("PUT", &writer, &[], "inside");
+    assert!(handler.handle_put(url("up/new.txt"), put).await.is_ok());
+
+    // `/dav/upload` 只是以 `/dav/up` 开头，不在前缀之下
+    let get = request("GET", &writer, &[], "");
+    let error = handler.handle_get(url("upload/file.txt"), get).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let put = request("PUT", &writer, &[], "outside");
+    let error = handler.handle_put(url("upload/new.txt"), put).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    let get = request("GET", &writer, &[], "");
+    let error = handler.handle_get(url("secret.txt"), get).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    // 复制的目标同样受限制
+    let destination = [("Destination", "http://localhost/dav/upload/copied.txt")];
+    let copy = request("COPY", &writer, &destination, "");
+    let error = handler.handle_copy(url("up/new.txt"), copy).await.unwrap_err();
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+    assert!(dir.path().join("up/new.txt").exists());
+    assert!(!dir.path().join("upload/new.txt").exists());
+    assert!(!dir.path().join("upload/copied.txt").exists());
+}
+
+#[tokio::test]
+async fn if_header_ignores_unreadable_resources() {
+    let (dir, handler) = setup();
+    let writer = token(Some("/dav/up"), Scope::ReadWrite);
+    let etag = etag_of(dir.path(), "secret.txt").await;
+
+    // 条件指向前缀之外的资源时不成立，不能借此探测它的 ETag 或是否存在
+    for condition in [format!("([{}])", etag), "(Not [\"guess\"])".to_string()] {
+        let if_header = format!("</dav/secret.txt> {}", condition);
+        let put = request("PUT", &writer, &[("If", &if_header)], "x");
+        let error = handler.handle_put(url("up/new.txt"), put).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::PRECONDITION_FAILED, "{}", if_header);
+    }
+    assert!(!dir.path().join("up/new.txt").exists());
+
+    // 可以读取的资源照常比较
+    std::fs::write(dir.path().join("up/file.txt"), "up").unwrap();
+    let etag = etag_of(dir.path(), "up/file.txt").await;
+    let if_header = format!("</dav/up/file.txt> ([{}])", etag);
+    let put = request("PUT", &writer, &[("If", &if_header)], "x");
+    assert!(handler.handle_put(url("up/new.txt"), put).await.is_ok());
+}
+
+async fn etag_of(root: &Path, path: &str) -> String {
+    let backend = FileSystemBackend::new(root);
+    backend.get_resource(Path::new(path)).await.unwrap().metadata.etag
+}